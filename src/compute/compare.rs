@@ -0,0 +1,228 @@
+use std::{collections::HashMap, env};
+
+use crate::utils::crypto::sha256hash;
+
+pub const DEFAULT_DKN_COMPARE_TEXT_THRESHOLD: f64 = 0.9;
+
+/// # Comparison
+///
+/// The outcome of comparing two results of the same task, see [`compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// Whether the results are byte-for-byte identical, compared by their hash.
+    pub exact: bool,
+    /// Word-level similarity of the normalized results, within `[0, 1]`.
+    pub similarity: f64,
+    /// Normalized words that are in the first result but not in the second.
+    pub removed: Vec<String>,
+    /// Normalized words that are in the second result but not in the first.
+    pub added: Vec<String>,
+}
+
+/// Thresholds above which two results are considered to agree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Minimum [`Comparison::similarity`] for texts to agree.
+    pub text: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            text: DEFAULT_DKN_COMPARE_TEXT_THRESHOLD,
+        }
+    }
+}
+
+impl Thresholds {
+    /// Reads `DKN_COMPARE_TEXT_THRESHOLD` from the environment, and defaults if not provided.
+    pub fn from_env() -> Self {
+        let text = match env::var("DKN_COMPARE_TEXT_THRESHOLD") {
+            Ok(value) if !value.trim().is_empty() => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| (0.0..=1.0).contains(value))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Invalid configuration: DKN_COMPARE_TEXT_THRESHOLD: {} is not within [0, 1]",
+                        value
+                    )
+                }),
+            _ => DEFAULT_DKN_COMPARE_TEXT_THRESHOLD,
+        };
+
+        Self { text }
+    }
+
+    /// Returns `true` if the compared texts agree.
+    #[inline]
+    pub fn text_agrees(&self, comparison: &Comparison) -> bool {
+        comparison.exact || comparison.similarity >= self.text
+    }
+}
+
+/// Returns `true` if both results have the same hash.
+#[inline]
+pub fn exact_match(a: impl AsRef<[u8]>, b: impl AsRef<[u8]>) -> bool {
+    sha256hash(a) == sha256hash(b)
+}
+
+/// Normalizes a text for comparison, by lowercasing it and collapsing its whitespace.
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Compares two results, with a word-level diff of their normalized texts.
+///
+/// The similarity is `2 * common / (len(a) + len(b))` where `common` is the length of the longest common
+/// subsequence of words, so that identical texts have similarity 1 and texts without common words have 0.
+///
+/// The differing words are counted as multisets, i.e. a word that appears more often in one result than in the
+/// other is listed as many times as the difference, in the order of appearance.
+pub fn compare(a: &str, b: &str) -> Comparison {
+    let exact = exact_match(a, b);
+    let a = normalize(a);
+    let b = normalize(b);
+    let a = a.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>();
+    let b = b.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>();
+
+    let total = a.len() + b.len();
+    let similarity = if total == 0 {
+        1.0
+    } else {
+        2.0 * lcs_len(&a, &b) as f64 / total as f64
+    };
+
+    let removed = difference(&a, &b);
+    let added = difference(&b, &a);
+
+    Comparison {
+        exact,
+        similarity,
+        removed,
+        added,
+    }
+}
+
+/// Returns the length of the longest common subsequence of two sequences.
+///
+/// Only two rows of the table are kept, so memory is linear in the length of the shorter sequence.
+fn lcs_len<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+
+    // prev[j] is the LCS of the previous prefix of a and b[..j]
+    let mut prev = vec![0usize; b.len() + 1];
+    let mut curr = vec![0usize; b.len() + 1];
+    for x in a {
+        for (j, y) in b.iter().enumerate() {
+            curr[j + 1] = if x == y {
+                prev[j] + 1
+            } else {
+                prev[j + 1].max(curr[j])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Returns the words of `a` that are not in `b`, counting repeated words.
+fn difference(a: &[&str], b: &[&str]) -> Vec<String> {
+    let mut counts = HashMap::<&str, usize>::new();
+    for word in b {
+        *counts.entry(word).or_default() += 1;
+    }
+
+    a.iter()
+        .filter(|word| match counts.get_mut(*word) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .map(|word| word.to_string())
+        .collect()
+}
+
+/// Returns the cosine similarity of two embeddings.
+///
+/// Returns `None` if their lengths differ, or if either of them is a zero vector.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f64> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let (mut dot, mut norm_a, mut norm_b) = (0f64, 0f64, 0f64);
+    for (x, y) in a.iter().zip(b.iter()) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_text() {
+        let comparison = compare("The quick brown fox", "The quick brown fox");
+        assert!(comparison.exact);
+        assert_eq!(comparison.similarity, 1.0);
+
+        // normalization ignores case and whitespace, but not for the exact match
+        let comparison = compare("The  quick\nbrown fox", "the quick brown FOX");
+        assert!(!comparison.exact);
+        assert_eq!(comparison.similarity, 1.0);
+        assert!(comparison.removed.is_empty() && comparison.added.is_empty());
+
+        let comparison = compare("the quick brown fox", "the slow brown fox jumps");
+        assert_eq!(comparison.removed, vec!["quick"]);
+        assert_eq!(comparison.added, vec!["slow", "jumps"]);
+        assert!((comparison.similarity - 6.0 / 9.0).abs() < 1e-9);
+
+        assert_eq!(compare("", "").similarity, 1.0);
+        assert_eq!(compare("a b", "").similarity, 0.0);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), Some(1.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), Some(0.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0]), None);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), None);
+    }
+
+    #[test]
+    fn test_lcs_len() {
+        assert_eq!(lcs_len(&["a", "b", "c", "d"], &["b", "d"]), 2);
+        assert_eq!(lcs_len(&["b", "d"], &["a", "b", "c", "d"]), 2);
+        assert_eq!(lcs_len(&["a", "b"], &["b", "a"]), 1);
+        assert_eq!(lcs_len::<&str>(&[], &["a"]), 0);
+
+        // repeated words are matched once each
+        let comparison = compare("a a b", "a b b");
+        assert_eq!(comparison.removed, vec!["a"]);
+        assert_eq!(comparison.added, vec!["b"]);
+        assert!((comparison.similarity - 4.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = Thresholds { text: 0.5 };
+        assert!(thresholds.text_agrees(&compare("a b c d", "a b c e")));
+        assert!(!thresholds.text_agrees(&compare("a b c d", "e f g h")));
+    }
+}
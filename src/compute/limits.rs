@@ -0,0 +1,68 @@
+use crate::utils::units::env_size;
+
+/// The outcome of checking the size of a task against [`TaskSizeLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskSize {
+    /// Within the limits.
+    Normal,
+    /// Over the soft limit, the task is processed after the others.
+    Oversized,
+    /// Over the hard limit, the task is not processed.
+    Rejected,
+}
+
+/// # Task Size Limits
+///
+/// Limits on the payload size of incoming tasks, which protect the memory of the node against very large tasks.
+/// Tasks over the hard limit are refused, and tasks over the soft limit are processed after the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskSizeLimits {
+    pub soft: Option<usize>,
+    pub hard: Option<usize>,
+}
+
+impl TaskSizeLimits {
+    /// Reads the limits from `DKN_TASK_SOFT_MAX_SIZE` and `DKN_TASK_MAX_SIZE`, such as `512KiB`, there is no limit if not provided.
+    pub fn from_env() -> Self {
+        let read =
+            |key: &str| env_size(key).unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+
+        Self {
+            soft: read("DKN_TASK_SOFT_MAX_SIZE"),
+            hard: read("DKN_TASK_MAX_SIZE"),
+        }
+    }
+
+    /// Checks a payload size in bytes against the limits.
+    pub fn check(&self, size: usize) -> TaskSize {
+        if self.hard.is_some_and(|hard| size > hard) {
+            TaskSize::Rejected
+        } else if self.soft.is_some_and(|soft| size > soft) {
+            TaskSize::Oversized
+        } else {
+            TaskSize::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_size_limits() {
+        let limits = TaskSizeLimits {
+            soft: Some(100),
+            hard: Some(200),
+        };
+        assert_eq!(limits.check(100), TaskSize::Normal);
+        assert_eq!(limits.check(101), TaskSize::Oversized);
+        assert_eq!(limits.check(200), TaskSize::Oversized);
+        assert_eq!(limits.check(201), TaskSize::Rejected);
+
+        assert_eq!(
+            TaskSizeLimits::default().check(usize::MAX),
+            TaskSize::Normal
+        );
+    }
+}
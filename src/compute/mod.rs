@@ -1,5 +1,9 @@
+pub mod compare;
+pub mod limits;
 pub mod ollama;
 pub mod payload;
+pub mod shadow;
+pub mod task_id;
 
 #[cfg(feature = "search_python")]
 pub mod search_python;
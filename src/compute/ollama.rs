@@ -125,7 +125,7 @@ pub async fn use_model_with_prompt(
     model: &str,
     prompt: &str,
 ) -> (GenerationResponse, tokio::time::Duration) {
-    use crate::utils::get_current_time_nanos;
+    use crate::utils::{get_current_time_nanos, saturating::elapsed_nanos};
 
     let ollama = OllamaClient::new(None, None, Some(model.to_string()));
     ollama
@@ -140,8 +140,7 @@ pub async fn use_model_with_prompt(
         .generate(prompt)
         .await
         .expect("Should generate response");
-    let time_diff = get_current_time_nanos() - time;
-    let duration = tokio::time::Duration::from_nanos(time_diff as u64);
+    let duration = elapsed_nanos(time, get_current_time_nanos());
 
     (gen_res, duration)
 }
@@ -1,7 +1,14 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, to_string};
+use std::time::Duration;
 
-use crate::{errors::NodeResult, utils::filter::FilterPayload};
+use super::task_id::TaskId;
+use crate::{
+    errors::NodeResult,
+    utils::{
+        clock::Clock, crypto::HashAlgorithm, filter::FilterPayload, saturating::elapsed_nanos,
+    },
+};
 
 /// # Dria Task Response
 ///
@@ -9,7 +16,8 @@ use crate::{errors::NodeResult, utils::filter::FilterPayload};
 /// Plain result is signed by the compute node's private key, and a commitment is computed from the signature and plain result.
 ///
 /// To check the commitment, one must decrypt the ciphertext and parse plaintext from it,
-/// and compute the digest using SHA256. That digest will then be used for the signature check.
+/// and compute the digest using the algorithm given in `hash`, which is SHA256 if not given.
+/// That digest will then be used for the signature check.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TaskResponsePayload {
     /// A signature on the digest of plaintext result.
@@ -18,6 +26,16 @@ pub struct TaskResponsePayload {
     pub ciphertext: String,
     /// A commitment to `signature || result`.
     pub commitment: String,
+    /// The hash algorithm of the digests, only given if it is not SHA256.
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub hash: HashAlgorithm,
+    /// Region where the task was processed, only given for tasks with region constraints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// A signature on the digest of `task_id || region`, where the task id is in the form it was given, acknowledging
+    /// the region constraint of the task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region_signature: Option<String>,
 }
 
 impl TaskResponsePayload {
@@ -33,7 +51,7 @@ impl TaskResponsePayload {
 #[serde(rename_all = "camelCase")]
 pub struct TaskRequestPayload<T> {
     /// The unique identifier of the task.
-    pub(crate) task_id: String,
+    pub(crate) task_id: TaskId,
     /// The deadline of the task in nanoseconds.
     pub(crate) deadline: u128,
     /// The input to the compute function.
@@ -42,4 +60,112 @@ pub struct TaskRequestPayload<T> {
     pub(crate) filter: FilterPayload,
     /// The public key of the requester.
     pub(crate) public_key: String,
+    /// Regions where the task may be processed, the task can be processed anywhere if empty.
+    #[serde(default)]
+    pub(crate) regions: Vec<String>,
+}
+
+impl<T> TaskRequestPayload<T> {
+    /// Returns `true` if the deadline of the task has passed, according to the given clock.
+    #[inline]
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.time_left(clock).is_zero()
+    }
+
+    /// Returns the time left until the deadline of the task, which is zero if it has passed.
+    #[inline]
+    pub fn time_left(&self, clock: &dyn Clock) -> Duration {
+        elapsed_nanos(clock.now_nanos(), self.deadline)
+    }
+
+    /// Returns `true` if the task may be processed in the given region, see [`TaskRequestPayload::regions`].
+    ///
+    /// A node without a region can only process tasks without region constraints.
+    pub fn allows_region(&self, region: Option<&str>) -> bool {
+        if self.regions.is_empty() {
+            return true;
+        }
+
+        region.is_some_and(|region| {
+            self.regions
+                .iter()
+                .any(|allowed| allowed.trim().eq_ignore_ascii_case(region))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clock::TestClock;
+
+    #[test]
+    fn test_task_deadline() {
+        let task: TaskRequestPayload<String> = serde_json::from_value(json!({
+            "taskId": "81a63a34-96c6-4e5a-99b5-6b274d9de175",
+            "deadline": 1_000_000_000u64,
+            "input": "hello",
+            "filter": { "hex": "00", "hashes": 1 },
+            "publicKey": "00",
+        }))
+        .expect("Should parse task");
+
+        let clock = TestClock::new(0);
+        assert!(!task.is_expired(&clock));
+
+        clock.advance(Duration::from_millis(999));
+        assert!(!task.is_expired(&clock));
+
+        clock.advance(Duration::from_millis(1));
+        assert!(task.is_expired(&clock));
+        assert_eq!(task.time_left(&clock), Duration::ZERO);
+
+        // a clock far past the deadline does not wrap around
+        clock.set(u128::MAX);
+        assert!(task.is_expired(&clock));
+        assert_eq!(task.time_left(&clock), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_task_deadline_bounds() {
+        let mut task: TaskRequestPayload<String> = serde_json::from_value(json!({
+            "taskId": "81a63a34-96c6-4e5a-99b5-6b274d9de175",
+            "deadline": 0,
+            "input": "hello",
+            "filter": { "hex": "00", "hashes": 1 },
+            "publicKey": "00",
+        }))
+        .expect("Should parse task");
+
+        let clock = TestClock::new(0);
+        assert!(task.is_expired(&clock));
+
+        // a far away deadline is capped instead of overflowing
+        task.deadline = u128::MAX;
+        assert!(!task.is_expired(&clock));
+        assert_eq!(task.time_left(&clock), Duration::from_nanos(u64::MAX));
+
+        clock.set(u128::MAX);
+        assert!(task.is_expired(&clock));
+    }
+
+    #[test]
+    fn test_task_regions() {
+        let mut task: TaskRequestPayload<String> = serde_json::from_value(json!({
+            "taskId": "81a63a34-96c6-4e5a-99b5-6b274d9de175",
+            "deadline": 0,
+            "input": "hello",
+            "filter": { "hex": "00", "hashes": 1 },
+            "publicKey": "00",
+        }))
+        .expect("Should parse task");
+        assert!(task.allows_region(None));
+        assert!(task.allows_region(Some("eu")));
+
+        task.regions = vec!["EU".to_string(), "ch".to_string()];
+        assert!(task.allows_region(Some("eu")));
+        assert!(task.allows_region(Some("ch")));
+        assert!(!task.allows_region(Some("us")));
+        assert!(!task.allows_region(None));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::utils::http::BaseClient;
+use crate::utils::http::{BaseClient, HttpTimeouts};
 use serde_json::json;
 use std::env;
 
@@ -28,7 +28,8 @@ impl SearchPythonClient {
             _ => false,
         };
 
-        let client = BaseClient::new(url.to_string());
+        let client =
+            BaseClient::with_timeouts(url.to_string(), HttpTimeouts::from_env("SEARCH_AGENT"));
 
         Self {
             client,
@@ -0,0 +1,148 @@
+use std::{env, sync::Arc};
+use tokio::sync::Semaphore;
+
+use super::{
+    compare::{compare, Thresholds},
+    ollama::OllamaClient,
+    task_id::TaskId,
+};
+use crate::utils::crypto::sha256hash;
+
+pub const DEFAULT_DKN_SHADOW_SAMPLE_RATE: f64 = 0.1;
+
+/// Number of shadow generations that may run at once.
+const SHADOW_CONCURRENCY: usize = 1;
+
+/// # Shadow Mode
+///
+/// Runs a candidate model alongside the primary one for a sampled fraction of tasks, so that a new model
+/// can be canaried before switching to it. The results are compared locally, and only the primary result is published.
+///
+/// Shadows run in the background after the primary result is published, so they never delay other tasks.
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    pub ollama: OllamaClient,
+    pub sample_rate: f64,
+    pub thresholds: Thresholds,
+    /// Permits of the running shadows, shared by clones.
+    permits: Arc<Semaphore>,
+}
+
+impl Shadow {
+    /// Creates a shadow from `DKN_SHADOW_OLLAMA_MODEL` and `DKN_SHADOW_SAMPLE_RATE`.
+    ///
+    /// Returns `None` if no shadow model is given, in which case shadow mode is disabled.
+    pub fn from_env() -> Option<Self> {
+        let model = env::var("DKN_SHADOW_OLLAMA_MODEL").unwrap_or_default();
+        if model.trim().is_empty() {
+            return None;
+        }
+
+        let sample_rate = match env::var("DKN_SHADOW_SAMPLE_RATE") {
+            Ok(rate) if !rate.trim().is_empty() => rate
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| (0.0..=1.0).contains(rate))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Invalid configuration: DKN_SHADOW_SAMPLE_RATE: {} is not within [0, 1]",
+                        rate
+                    )
+                }),
+            _ => DEFAULT_DKN_SHADOW_SAMPLE_RATE,
+        };
+        log::info!("Shadow sample rate: {}", sample_rate);
+
+        Some(Self {
+            ollama: OllamaClient::new(None, None, Some(model.trim().to_string())),
+            sample_rate,
+            thresholds: Thresholds::from_env(),
+            permits: Arc::new(Semaphore::new(SHADOW_CONCURRENCY)),
+        })
+    }
+
+    /// Returns `true` if the task should also be run by the shadow, see [`is_sampled`].
+    #[inline]
+    pub fn is_sampled(&self, task_id: &TaskId) -> bool {
+        is_sampled(task_id, self.sample_rate)
+    }
+
+    /// Generates the shadow result of a task in the background, and compares it with the primary result.
+    ///
+    /// The task is not shadowed if another shadow is still running, so that shadows do not pile up
+    /// when tasks arrive faster than the shadow model can keep up.
+    pub fn spawn(&self, task_id: TaskId, input: String, primary: String) {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                log::debug!("Skipping shadow for {}, another shadow is running", task_id);
+                return;
+            }
+        };
+
+        let shadow = self.clone();
+        tokio::spawn(async move {
+            match shadow.ollama.generate(input).await {
+                Ok(result) => shadow.compare(&task_id, &primary, &result.response),
+                Err(e) => log::error!("Error generating shadow result: {}", e),
+            }
+            drop(permit);
+        });
+    }
+
+    /// Compares the primary result with the shadow result, and logs the outcome.
+    pub fn compare(&self, task_id: &TaskId, primary: &str, shadow: &str) {
+        let comparison = compare(primary, shadow);
+        if comparison.exact {
+            log::info!("Shadow result for {} is identical", task_id);
+        } else {
+            log::info!(
+                "Shadow result for {} {}: similarity {:.3}",
+                task_id,
+                if self.thresholds.text_agrees(&comparison) {
+                    "agrees"
+                } else {
+                    "disagrees"
+                },
+                comparison.similarity
+            );
+            log::debug!(
+                "Shadow diff for {}: removed {:?}, added {:?}",
+                task_id,
+                comparison.removed,
+                comparison.added
+            );
+        }
+    }
+}
+
+/// Decides whether a task is sampled with the given rate.
+///
+/// The decision is derived from the hash of the task id, so that a task is always sampled the same way.
+pub fn is_sampled(task_id: &TaskId, sample_rate: f64) -> bool {
+    let digest = sha256hash(task_id.as_bytes());
+    let mut bucket = [0u8; 8];
+    bucket.copy_from_slice(&digest[..8]);
+    let bucket = u64::from_be_bytes(bucket) as f64 / u64::MAX as f64;
+
+    bucket < sample_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling() {
+        let task_id = TaskId::new();
+        assert!(!is_sampled(&task_id, 0.0));
+        assert!(is_sampled(&task_id, 1.0));
+        assert_eq!(is_sampled(&task_id, 0.5), is_sampled(&task_id, 0.5));
+
+        let sampled = (0..1000)
+            .filter(|_| is_sampled(&TaskId::new(), 0.1))
+            .count();
+        assert!(sampled > 50 && sampled < 150);
+    }
+}
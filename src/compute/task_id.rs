@@ -0,0 +1,272 @@
+use core::fmt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+use crate::utils::{crypto::sha256hash, get_current_time_nanos, saturating::nanos_to_u64};
+
+/// Maximum length of a task id, as it is also the name of the topic that its result is published to.
+const MAX_TASK_ID_LEN: usize = 128;
+
+/// Largest value of the 12-bit counter that orders UUIDv7 ids within the same millisecond.
+const MAX_COUNTER: u16 = 0x0fff;
+
+/// # Task ID
+///
+/// The unique identifier of a task, such as a [UUID](https://www.rfc-editor.org/rfc/rfc9562) like
+/// `81a63a34-96c6-4e5a-99b5-6b274d9de175` or a hex-encoded hash. The id is kept exactly as it was given, as the
+/// result of a task is published to a topic named after its id; it must be non-empty, at most 128 characters, and
+/// consist of alphanumerics, `-`, `_` and `.` only, like any topic.
+///
+/// Ids in the hyphenated UUID form are detected regardless of case, so that their version and creation time can
+/// be read. Task ids created with [`TaskId::new`] are UUIDv7, which are ordered by their creation time.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TaskId {
+    id: String,
+    uuid: Option<[u8; 16]>,
+}
+
+impl TaskId {
+    /// Creates a new UUIDv7 task id with the current time.
+    ///
+    /// Ids created within the same millisecond are ordered by a 12-bit counter that is reset on each millisecond,
+    /// and the remaining bits are derived from the current time in nanoseconds. If the counter overflows, or the
+    /// clock goes backwards, the millisecond of the previous id is carried forward, so that ids created by this
+    /// process are always in creation order.
+    pub fn new() -> Self {
+        static LAST: Mutex<(u64, u16)> = Mutex::new((0, 0));
+        let nanos = get_current_time_nanos();
+        let (millis, counter) = {
+            let mut last = LAST.lock();
+            let millis = nanos_to_u64(nanos / 1_000_000);
+            *last = if millis > last.0 {
+                (millis, 0)
+            } else if last.1 < MAX_COUNTER {
+                (last.0, last.1 + 1)
+            } else {
+                (last.0 + 1, 0)
+            };
+            *last
+        };
+        let entropy = sha256hash([nanos.to_be_bytes().as_slice(), &counter.to_be_bytes()].concat());
+
+        let mut bytes = [0u8; 16];
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+        bytes[6] = 0x70 | ((counter >> 8) & 0x0f) as u8; // version 7
+        bytes[7] = counter as u8;
+        bytes[8..].copy_from_slice(&entropy[..8]);
+        bytes[8] = 0x80 | (bytes[8] & 0x3f); // RFC 9562 variant
+
+        let hex = hex::encode(bytes);
+        TaskId {
+            id: format!(
+                "{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            ),
+            uuid: Some(bytes),
+        }
+    }
+
+    /// Returns the UUID bytes, if the id is a UUID.
+    #[inline]
+    pub fn uuid(&self) -> Option<&[u8; 16]> {
+        self.uuid.as_ref()
+    }
+
+    /// Returns the version of the UUID, if the id is a UUID.
+    #[inline]
+    pub fn version(&self) -> Option<u8> {
+        self.uuid.map(|uuid| uuid[6] >> 4)
+    }
+
+    /// Returns the creation time in milliseconds since the Unix epoch, if this is a UUIDv7.
+    pub fn timestamp_millis(&self) -> Option<u64> {
+        let uuid = self.uuid.filter(|_| self.version() == Some(7))?;
+
+        let mut millis = [0u8; 8];
+        millis[2..].copy_from_slice(&uuid[..6]);
+        Some(u64::from_be_bytes(millis))
+    }
+
+    /// Returns the id as it was given.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the bytes of the id as it was given.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.id.as_bytes()
+    }
+}
+
+impl Default for TaskId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Task ids are ordered by their creation time if they are UUIDv7, which come after other ids.
+impl Ord for TaskId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.timestamp_millis(), self.uuid, &self.id).cmp(&(
+            other.timestamp_millis(),
+            other.uuid,
+            &other.id,
+        ))
+    }
+}
+
+impl PartialOrd for TaskId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Parses a UUID in its hyphenated form, in any case.
+fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    let is_uuid = s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    if !is_uuid {
+        return None;
+    }
+
+    let mut bytes = [0u8; 16];
+    hex::decode_to_slice(s.replace('-', ""), &mut bytes).ok()?;
+    Some(bytes)
+}
+
+impl FromStr for TaskId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.len() > MAX_TASK_ID_LEN {
+            return Err(format!("Invalid task id length: {}", s.len()));
+        }
+        if let Some(c) = s
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        {
+            return Err(format!("Invalid character {:?} in task id {}", c, s));
+        }
+
+        Ok(TaskId {
+            id: s.to_string(),
+            uuid: parse_uuid(s),
+        })
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl fmt::Debug for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TaskId({})", self)
+    }
+}
+
+impl Serialize for TaskId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TaskId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_ID: &str = "81a63a34-96c6-4e5a-99b5-6b274d9de175";
+
+    #[test]
+    fn test_parse_display() {
+        let task_id: TaskId = TASK_ID.parse().expect("Should parse");
+        assert_eq!(task_id.to_string(), TASK_ID);
+        assert_eq!(task_id.version(), Some(4));
+        assert_eq!(task_id.timestamp_millis(), None);
+
+        let json = serde_json::to_string(&task_id).expect("Should serialize");
+        assert_eq!(json, format!("\"{}\"", TASK_ID));
+        let parsed: TaskId = serde_json::from_str(&json).expect("Should deserialize");
+        assert_eq!(parsed, task_id);
+    }
+
+    #[test]
+    fn test_parse_other_ids() {
+        // uppercase UUIDs are detected, and displayed as given
+        let upper = TASK_ID.to_uppercase();
+        let task_id: TaskId = upper.parse().expect("Should parse");
+        assert_eq!(task_id.to_string(), upper);
+        assert_eq!(task_id.version(), Some(4));
+
+        // hash-based ids are kept as they are, without a UUID
+        let hash = hex::encode(sha256hash(b"task"));
+        let task_id: TaskId =
+            serde_json::from_str(&format!("\"{}\"", hash)).expect("Should deserialize");
+        assert_eq!(task_id.to_string(), hash);
+        assert_eq!(task_id.as_bytes(), hash.as_bytes());
+        assert_eq!(task_id.uuid(), None);
+        assert_eq!(task_id.version(), None);
+        assert_eq!(task_id.timestamp_millis(), None);
+
+        // a UUID without hyphens is not detected as one
+        let task_id: TaskId = TASK_ID.replace('-', "").parse().expect("Should parse");
+        assert_eq!(task_id.uuid(), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("".parse::<TaskId>().is_err());
+        assert!("task/id".parse::<TaskId>().is_err());
+        assert!("task id".parse::<TaskId>().is_err());
+        assert!("a".repeat(MAX_TASK_ID_LEN + 1).parse::<TaskId>().is_err());
+        assert!(serde_json::from_str::<TaskId>("\"\"").is_err());
+    }
+
+    #[test]
+    fn test_new_ordered() {
+        let first = TaskId::new();
+        let second = TaskId::new();
+        assert!(first < second);
+        assert_eq!(first.version(), Some(7));
+        assert_eq!(first.uuid().expect("Should be a UUID")[8] >> 6, 0b10);
+
+        let millis = (get_current_time_nanos() / 1_000_000) as u64;
+        let created = first.timestamp_millis().expect("Should have timestamp");
+        assert!(created <= millis && millis - created < 1000);
+
+        // round-trip through its string form
+        assert_eq!(first.to_string().parse::<TaskId>(), Ok(first.clone()));
+
+        // UUIDv7 ids come after other ids
+        let other: TaskId = TASK_ID.parse().expect("Should parse");
+        assert!(other < first);
+    }
+
+    #[test]
+    fn test_new_ordered_past_counter() {
+        // more ids than the counter can hold within a millisecond
+        let ids: Vec<TaskId> = (0..=3 * MAX_COUNTER as usize)
+            .map(|_| TaskId::new())
+            .collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,125 @@
+use libsecp256k1::SecretKey;
+use std::{env, fmt::Debug, fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+use crate::errors::NodeResult;
+
+/// # Identity Provider
+///
+/// Loads the secret key of the node from wherever it is stored, selected with `DKN_IDENTITY`, see [`identity_from_env`].
+pub trait IdentityProvider: Debug {
+    /// Name of the provider, for logs and errors.
+    fn name(&self) -> &'static str;
+
+    /// Loads the secret key.
+    fn load(&self) -> NodeResult<SecretKey>;
+}
+
+/// Reads a hex-encoded secret key from an environment variable, which is `DKN_WALLET_SECRET_KEY` by default.
+#[derive(Debug, Clone)]
+pub struct EnvIdentity {
+    pub key: String,
+}
+
+impl Default for EnvIdentity {
+    fn default() -> Self {
+        Self {
+            key: "DKN_WALLET_SECRET_KEY".to_string(),
+        }
+    }
+}
+
+impl IdentityProvider for EnvIdentity {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    fn load(&self) -> NodeResult<SecretKey> {
+        let secret = env::var(&self.key).map_err(|_| format!("{} is not set", self.key))?;
+        parse_secret(&secret)
+    }
+}
+
+/// Reads a hex-encoded secret key from a file, given with `DKN_WALLET_KEYFILE`.
+///
+/// The file should only be readable by its owner, a warning is logged otherwise.
+#[derive(Debug, Clone)]
+pub struct KeyfileIdentity {
+    pub path: PathBuf,
+}
+
+impl IdentityProvider for KeyfileIdentity {
+    fn name(&self) -> &'static str {
+        "keyfile"
+    }
+
+    fn load(&self) -> NodeResult<SecretKey> {
+        let metadata = fs::metadata(&self.path)?;
+        if metadata.permissions().mode() & 0o077 != 0 {
+            log::warn!(
+                "Keyfile {} is accessible by other users, consider `chmod 600`",
+                self.path.display()
+            );
+        }
+
+        let secret = fs::read_to_string(&self.path)?;
+        parse_secret(&secret)
+    }
+}
+
+/// Parses a hex-encoded secret key, with or without the `0x` prefix.
+pub fn parse_secret(secret: &str) -> NodeResult<SecretKey> {
+    let secret = secret.trim();
+    let secret = hex::decode(secret.strip_prefix("0x").unwrap_or(secret))?;
+    SecretKey::parse_slice(&secret)
+        .map_err(|e| format!("Secret key should be 32-bytes: {:?}", e).into())
+}
+
+/// Selects the identity provider with `DKN_IDENTITY`, which is either `env` (default) or `keyfile`.
+pub fn identity_from_env() -> NodeResult<Box<dyn IdentityProvider>> {
+    let identity = env::var("DKN_IDENTITY").unwrap_or_default();
+    match identity.trim() {
+        "" | "env" => Ok(Box::new(EnvIdentity::default())),
+        "keyfile" => {
+            let path = env::var("DKN_WALLET_KEYFILE")
+                .map_err(|_| "DKN_WALLET_KEYFILE should be provided for the keyfile identity")?;
+            Ok(Box::new(KeyfileIdentity { path: path.into() }))
+        }
+        other => Err(format!("DKN_IDENTITY: unknown identity provider {:?}", other).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "6e6f64656e6f64656e6f64656e6f64656e6f64656e6f64656e6f64656e6f6465";
+
+    #[test]
+    fn test_env_identity() {
+        env::set_var("TEST_IDENTITY_SECRET_KEY", format!("0x{}", SECRET));
+        let identity = EnvIdentity {
+            key: "TEST_IDENTITY_SECRET_KEY".to_string(),
+        };
+        let secret_key = identity.load().expect("Should load");
+        assert_eq!(hex::encode(secret_key.serialize()), SECRET);
+
+        env::set_var("TEST_IDENTITY_SECRET_KEY", "1234");
+        assert!(identity.load().is_err());
+        env::remove_var("TEST_IDENTITY_SECRET_KEY");
+        assert!(identity.load().is_err());
+    }
+
+    #[test]
+    fn test_keyfile_identity() {
+        let path = env::temp_dir().join("dkn-test-keyfile");
+        fs::write(&path, format!("{}\n", SECRET)).expect("Should write");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).expect("Should chmod");
+
+        let identity = KeyfileIdentity { path: path.clone() };
+        let secret_key = identity.load().expect("Should load");
+        assert_eq!(hex::encode(secret_key.serialize()), SECRET);
+
+        fs::remove_file(&path).expect("Should remove");
+        assert!(identity.load().is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::utils::crypto::to_address;
+pub mod identity;
+
+use crate::utils::crypto::{to_address, HashAlgorithm};
 use ecies::PublicKey;
 use libsecp256k1::{PublicKeyFormat, SecretKey};
 use std::env;
@@ -24,6 +26,10 @@ pub struct DriaComputeNodeConfig {
     pub DKN_WALLET_ADDRESS: [u8; 20],
     /// Admin public key, used for message authenticity.
     pub DKN_ADMIN_PUBLIC_KEY: PublicKey,
+    /// Region where this node processes tasks, e.g. `eu`, used for geo-fenced tasks.
+    pub DKN_REGION: Option<String>,
+    /// Hash algorithm of the digests in result payloads.
+    pub DKN_HASH_ALGORITHM: HashAlgorithm,
 }
 
 #[cfg(test)]
@@ -33,10 +39,12 @@ fn prepare_secret() -> SecretKey {
 
 #[cfg(not(test))]
 fn prepare_secret() -> SecretKey {
-    let secret_env =
-        env::var("DKN_WALLET_SECRET_KEY").expect("Secret key should be provided in .env.");
-    let secret_dec = hex::decode(secret_env).expect("Secret key should be 32-bytes hex encoded.");
-    SecretKey::parse_slice(&secret_dec).expect("Secret key should be parseable.")
+    let provider =
+        identity::identity_from_env().unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+    log::info!("Loading secret key from {}", provider.name());
+    provider
+        .load()
+        .unwrap_or_else(|e| panic!("Could not load secret key from {}: {}", provider.name(), e))
 }
 
 impl DriaComputeNodeConfig {
@@ -58,6 +66,18 @@ impl DriaComputeNodeConfig {
 
         let address = to_address(&public_key);
 
+        let hash_algorithm = match env::var("DKN_HASH_ALGORITHM") {
+            Ok(algorithm) if !algorithm.trim().is_empty() => algorithm
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid configuration: DKN_HASH_ALGORITHM: {}", e)),
+            _ => HashAlgorithm::default(),
+        };
+
+        let region = env::var("DKN_REGION")
+            .map(|region| region.trim().to_lowercase())
+            .ok()
+            .filter(|region| !region.is_empty());
+
         log::info!("Address:    0x{}", hex::encode(address));
         log::info!(
             "Node Public Key: 0x{}",
@@ -72,12 +92,17 @@ impl DriaComputeNodeConfig {
             "Admin Public Key: 0x{}",
             hex::encode(admin_public_key.serialize_compressed())
         );
+        if let Some(region) = &region {
+            log::info!("Region: {}", region);
+        }
 
         Self {
             DKN_ADMIN_PUBLIC_KEY: admin_public_key,
             DKN_WALLET_SECRET_KEY: secret_key,
             DKN_WALLET_PUBLIC_KEY: public_key,
             DKN_WALLET_ADDRESS: address,
+            DKN_REGION: region,
+            DKN_HASH_ALGORITHM: hash_algorithm,
         }
     }
 }
@@ -2,15 +2,81 @@ use serde::Deserialize;
 
 pub type NodeResult<T> = std::result::Result<T, NodeError>;
 
+/// # Error Kind
+///
+/// Classification of a [`NodeError`], used to decide whether an operation is worth retrying.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    /// A temporary failure such as a timeout or an unavailable service, that may succeed on retry.
+    Transient,
+    /// A failure that will not succeed on retry, such as a rejected request.
+    Permanent,
+    /// An authentication or authorization failure.
+    Auth,
+    /// A malformed message, payload or encoding.
+    Protocol,
+    /// A failure within the node itself.
+    #[default]
+    Internal,
+}
+
+impl ErrorKind {
+    /// Returns `true` if an operation that failed with this kind of error may succeed on retry.
+    #[inline]
+    pub fn is_retryable(&self) -> bool {
+        *self == ErrorKind::Transient
+    }
+
+    /// Returns `true` if an operation that failed with this kind of error will not succeed no matter how long it is
+    /// retried, unlike e.g. a `4xx` response from a service that is still starting up.
+    #[inline]
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ErrorKind::Auth | ErrorKind::Protocol)
+    }
+
+    /// Classifies an HTTP error response by its status code.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorKind::Auth,
+            429 | 500..=599 => ErrorKind::Transient,
+            _ => ErrorKind::Permanent,
+        }
+    }
+}
+
 /// # Node Error
 ///
 /// A generic error within the Compute Node. This may originate from serde, reqwest and such. The source is
 /// included along the error message, and `From` traits are implemented for expected errors.
+///
+/// Errors are classified with an [`ErrorKind`], and the originating error is kept as the
+/// [`std::error::Error::source`] of this error so that the chain is preserved.
 #[derive(Deserialize)]
 pub struct NodeError {
     #[serde(rename = "error")]
     pub message: String,
     pub source: String,
+    #[serde(default)]
+    pub kind: ErrorKind,
+    #[serde(skip)]
+    cause: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl NodeError {
+    /// Creates an error from a downstream error, keeping it as the source of this error.
+    fn from_error(
+        error: impl std::error::Error + Send + Sync + 'static,
+        source: &str,
+        kind: ErrorKind,
+    ) -> Self {
+        Self {
+            message: error.to_string(),
+            source: source.to_string(),
+            kind,
+            cause: Some(Box::new(error)),
+        }
+    }
 }
 
 impl std::fmt::Display for NodeError {
@@ -25,13 +91,21 @@ impl std::fmt::Debug for NodeError {
     }
 }
 
-impl std::error::Error for NodeError {}
+impl std::error::Error for NodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
 
 impl From<String> for NodeError {
     fn from(message: String) -> Self {
         Self {
             message,
             source: "self".to_string(),
+            kind: ErrorKind::Internal,
+            cause: None,
         }
     }
 }
@@ -41,43 +115,51 @@ impl From<&str> for NodeError {
         Self {
             message: message.to_string(),
             source: "self".to_string(),
+            kind: ErrorKind::Internal,
+            cause: None,
         }
     }
 }
 
 impl From<reqwest::Error> for NodeError {
     fn from(value: reqwest::Error) -> Self {
-        Self {
-            message: value.to_string(),
-            source: "reqwest".to_string(),
-        }
+        let kind = match value.status() {
+            Some(status) => ErrorKind::from_status(status.as_u16()),
+            None if value.is_decode() => ErrorKind::Protocol,
+            None => ErrorKind::Transient, // timeouts, connection errors and such
+        };
+
+        Self::from_error(value, "reqwest", kind)
     }
 }
 
 impl From<serde_json::Error> for NodeError {
     fn from(value: serde_json::Error) -> Self {
-        Self {
-            message: value.to_string(),
-            source: "serde_json".to_string(),
-        }
+        Self::from_error(value, "serde_json", ErrorKind::Protocol)
     }
 }
 
 impl From<base64::DecodeError> for NodeError {
     fn from(value: base64::DecodeError) -> Self {
-        Self {
-            message: value.to_string(),
-            source: "base64".to_string(),
-        }
+        Self::from_error(value, "base64", ErrorKind::Protocol)
     }
 }
 
 impl From<hex::FromHexError> for NodeError {
     fn from(value: hex::FromHexError) -> Self {
-        Self {
-            message: value.to_string(),
-            source: "hex".to_string(),
-        }
+        Self::from_error(value, "hex", ErrorKind::Protocol)
+    }
+}
+
+impl From<std::io::Error> for NodeError {
+    fn from(value: std::io::Error) -> Self {
+        Self::from_error(value, "io", ErrorKind::Internal)
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for NodeError {
+    fn from(value: tokio::time::error::Elapsed) -> Self {
+        Self::from_error(value, "tokio", ErrorKind::Transient)
     }
 }
 
@@ -86,6 +168,56 @@ impl From<ecies::SecpError> for NodeError {
         Self {
             message: value.to_string(),
             source: "secp256k1".to_string(),
+            kind: ErrorKind::Protocol,
+            cause: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_error_kind() {
+        let err = NodeError::from("something went wrong");
+        assert_eq!(err.kind, ErrorKind::Internal);
+        assert!(!err.kind.is_retryable());
+        assert!(err.source().is_none());
+
+        let err = NodeError::from(hex::decode("not hex").expect_err("Should fail"));
+        assert_eq!(err.kind, ErrorKind::Protocol);
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_status_kind() {
+        // a relay that is still starting up may respond with these, so they are not fatal
+        for status in [400, 404, 409] {
+            assert_eq!(ErrorKind::from_status(status), ErrorKind::Permanent);
+            assert!(!ErrorKind::from_status(status).is_fatal());
+        }
+        for status in [429, 500, 503] {
+            assert_eq!(ErrorKind::from_status(status), ErrorKind::Transient);
+            assert!(ErrorKind::from_status(status).is_retryable());
+        }
+        for status in [401, 403] {
+            assert_eq!(ErrorKind::from_status(status), ErrorKind::Auth);
+            assert!(ErrorKind::from_status(status).is_fatal());
+        }
+        assert!(ErrorKind::Protocol.is_fatal());
+        assert!(!ErrorKind::Internal.is_fatal());
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let json_err = serde_json::from_str::<u8>("{").expect_err("Should fail");
+        let message = json_err.to_string();
+        let err = NodeError::from(json_err);
+
+        let cause = err.source().expect("Should have a source");
+        assert_eq!(cause.to_string(), message);
+        assert!(cause.downcast_ref::<serde_json::Error>().is_some());
+    }
+}
@@ -1,12 +1,15 @@
-use dkn_compute::utils::wait_for_termination;
+use dkn_compute::utils::{archive::Archiver, wait_for_termination};
 use dkn_compute::{config::DriaComputeNodeConfig, node::DriaComputeNode};
+use std::env;
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
 
-// diagnostic & heartbeat always enabled
+// capabilities, diagnostic, heartbeat & publish always enabled
+use dkn_compute::workers::capabilities::*;
 use dkn_compute::workers::diagnostic::*;
 use dkn_compute::workers::heartbeat::*;
+use dkn_compute::workers::publish::*;
 
 #[cfg(feature = "synthesis")]
 use dkn_compute::workers::synthesis::*;
@@ -23,9 +26,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
     log::info!("Using Dria Compute Node v{}", VERSION);
 
+    // purge expired archives and exit
+    if env::args().any(|arg| arg == "--purge") {
+        match Archiver::from_env() {
+            Some(archive) => log::info!("Purged {} archive files", archive.purge()?),
+            None => log::warn!("Archiving is not enabled, nothing to purge"),
+        }
+        return Ok(());
+    }
+
     let config = DriaComputeNodeConfig::new();
     let cancellation = CancellationToken::new();
-    let node = Arc::new(DriaComputeNode::new(config, cancellation.clone()));
+    let mut node = DriaComputeNode::new(config, cancellation.clone());
+    if env::args().any(|arg| arg == "--dry-run") {
+        let dir = env::var("DKN_DRY_RUN_DIR").unwrap_or("dry-run".to_string());
+        log::warn!("Dry-run enabled, results will be written to {}", dir);
+        node = node.with_dry_run(dir.into());
+    }
+    let node = Arc::new(node);
+    node.set_panic_hook();
 
     log::info!("Starting workers");
     let tracker = TaskTracker::new();
@@ -38,6 +57,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         node.clone(),
         tokio::time::Duration::from_secs(60),
     ));
+    tracker.spawn(publish_worker(node.clone()));
+    tracker.spawn(capabilities_worker(
+        node.clone(),
+        tokio::time::Duration::from_secs(30),
+    ));
 
     #[cfg(feature = "synthesis")]
     tracker.spawn(synthesis_worker(
@@ -60,6 +84,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     wait_for_termination(cancellation).await?;
     log::warn!("Stopping workers");
     tracker.wait().await;
+    node.flush_archive();
+
+    // exit with an error so that the node is restarted
+    if node.has_panicked() {
+        return Err("Node stopped due to a panic".into());
+    }
 
     Ok(())
 }
@@ -2,14 +2,41 @@ use ecies::encrypt;
 use fastbloom_rs::{BloomFilter, Membership};
 use libsecp256k1::{sign, Message, RecoveryId, Signature};
 use parking_lot::RwLock;
+use serde::Serialize;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    compute::payload::TaskResponsePayload,
+    compute::{
+        limits::{TaskSize, TaskSizeLimits},
+        payload::TaskResponsePayload,
+        task_id::TaskId,
+    },
     config::DriaComputeNodeConfig,
     errors::NodeResult,
-    utils::{crypto::sha256hash, filter::FilterPayload},
-    waku::{message::WakuMessage, WakuClient},
+    utils::{
+        archive::{ArchiveKind, Archiver},
+        capabilities::{Capabilities, SignedCapabilities},
+        clock::{Clock, SystemClock},
+        crypto::sha256hash,
+        filter::FilterPayload,
+        metrics::{NodeMetrics, NoopMetrics},
+        timing::timed,
+    },
+    waku::{
+        message::{WakuMessage, TASK_TOPICS},
+        network::NetworkConfig,
+        outbox::Outbox,
+        WakuClient,
+    },
 };
 
 #[allow(unused)]
@@ -19,6 +46,24 @@ pub struct DriaComputeNode {
     pub waku: WakuClient,
     pub cancellation: CancellationToken,
     pub busy_lock: RwLock<bool>,
+    pub tasks_lock: RwLock<Vec<TaskId>>,
+    pub archive: Option<Arc<Archiver>>,
+    pub clock: Arc<dyn Clock>,
+    /// Namespace of the content topics that the node subscribes to and publishes on.
+    pub network: NetworkConfig,
+    pub metrics: Arc<dyn NodeMetrics>,
+    pub dry_run: Option<PathBuf>,
+    /// Results that are waiting to be published by the publish worker.
+    pub outbox: Outbox,
+    /// Number of messages that were expected to be signed by Dria, but were not.
+    pub signature_failures: AtomicU64,
+    pub task_limits: TaskSizeLimits,
+    /// Number of tasks that were over the soft size limit, and were processed after the others.
+    pub oversized_tasks: AtomicU64,
+    /// Number of messages on task topics that were over a size limit, but were not verified to be tasks from Dria:
+    /// either they were over the hard size limit and refused before verification, or they failed verification.
+    pub unverified_oversized: AtomicU64,
+    panicked: AtomicBool,
 }
 
 impl Default for DriaComputeNode {
@@ -31,14 +76,59 @@ impl DriaComputeNode {
     pub fn new(config: DriaComputeNodeConfig, cancellation: CancellationToken) -> Self {
         let waku = WakuClient::new(None);
         let busy_lock = RwLock::new(false);
+        let tasks_lock = RwLock::new(Vec::new());
+        let archive = Archiver::from_env().map(Arc::new);
         DriaComputeNode {
             config,
             waku,
             cancellation,
             busy_lock,
+            tasks_lock,
+            archive,
+            clock: Arc::new(SystemClock),
+            network: NetworkConfig::from_env(),
+            metrics: Arc::new(NoopMetrics),
+            dry_run: None,
+            outbox: Outbox::from_env(),
+            signature_failures: AtomicU64::new(0),
+            task_limits: TaskSizeLimits::from_env(),
+            oversized_tasks: AtomicU64::new(0),
+            unverified_oversized: AtomicU64::new(0),
+            panicked: AtomicBool::new(false),
         }
     }
 
+    /// Replaces the clock of the node, which is used for deadlines and message timestamps.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replaces the network of the node, which is read from the environment by default.
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Replaces the metrics backend of the node, which is a no-op by default.
+    pub fn with_metrics(mut self, metrics: Arc<dyn NodeMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Enables dry-run, where results are written to files under the given directory instead of being published.
+    ///
+    /// Heartbeats are not answered in dry-run, see [`crate::workers::heartbeat::heartbeat_worker`].
+    pub fn with_dry_run(mut self, dir: PathBuf) -> Self {
+        self.dry_run = Some(dir);
+        self
+    }
+
+    /// Creates a message on the given topic within the network of the node, timestamped by its clock.
+    pub fn create_message(&self, payload: impl AsRef<[u8]>, topic: &str) -> WakuMessage {
+        WakuMessage::new_with_network(payload, topic, &self.network, self.clock.as_ref())
+    }
+
     /// Returns the wallet address of the node.
     #[inline]
     pub fn address(&self) -> [u8; 20] {
@@ -63,6 +153,84 @@ impl DriaComputeNode {
         *self.busy_lock.write() = busy;
     }
 
+    /// Returns the ids of the tasks that the node is currently working on.
+    #[inline]
+    pub fn current_tasks(&self) -> Vec<TaskId> {
+        self.tasks_lock.read().clone()
+    }
+
+    /// Set the ids of the tasks that the node is currently working on.
+    #[inline]
+    pub fn set_current_tasks(&self, task_ids: Vec<TaskId>) {
+        *self.tasks_lock.write() = task_ids;
+    }
+
+    /// Returns whether the node has panicked, see [`DriaComputeNode::set_panic_hook`].
+    #[inline]
+    pub fn has_panicked(&self) -> bool {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    /// Sets a panic hook that logs the panic along with the node context, and cancels the node so that
+    /// it stops responding to heartbeats and shuts down, instead of running without the panicked worker.
+    ///
+    /// The default hook is called afterwards, so the usual panic message & backtrace are still printed.
+    ///
+    /// The hook only keeps a weak reference to the node, so that the node can still be dropped.
+    pub fn set_panic_hook(self: &Arc<Self>) {
+        let node = Arc::downgrade(self);
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let node = match node.upgrade() {
+                Some(node) => node,
+                None => return default_hook(info),
+            };
+            node.panicked.store(true, Ordering::Relaxed);
+
+            // the lock may be held by the panicking thread, so we dont wait for it
+            let tasks = node
+                .tasks_lock
+                .try_read()
+                .map(|tasks| {
+                    tasks
+                        .iter()
+                        .map(TaskId::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .unwrap_or_default();
+            log::error!(
+                "panic address=0x{} version={} thread={} tasks=[{}] info=\"{}\"",
+                hex::encode(node.address()),
+                env!("CARGO_PKG_VERSION"),
+                std::thread::current().name().unwrap_or("unnamed"),
+                tasks,
+                info
+            );
+
+            node.cancellation.cancel();
+            default_hook(info);
+        }));
+    }
+
+    /// Finishes the current archive file, if archiving is enabled, so that it is complete and recorded in the manifest.
+    pub fn flush_archive(&self) {
+        if let Some(archive) = &self.archive {
+            if let Err(e) = archive.flush() {
+                log::error!("Error flushing archive: {}", e);
+            }
+        }
+    }
+
+    /// Archives an inbound task or an outbound result, if archiving is enabled.
+    pub fn archive(&self, kind: ArchiveKind, body: &impl Serialize) {
+        if let Some(archive) = &self.archive {
+            if let Err(e) = archive.archive(kind, body) {
+                log::error!("Error archiving: {}", e);
+            }
+        }
+    }
+
     /// Shorthand to sign a digest (bytes) with node's secret key and return signature & recovery id
     /// serialized to 65 byte hex-string.
     #[inline]
@@ -87,48 +255,115 @@ impl DriaComputeNode {
         Ok(filter.contains(&self.address()))
     }
 
+    /// Returns the current capabilities of the node, signed with its secret key.
+    pub fn capabilities(&self) -> SignedCapabilities {
+        #[allow(unused_mut)]
+        let mut topics = Vec::new();
+        #[cfg(feature = "synthesis")]
+        topics.push("synthesis".to_string());
+        #[cfg(feature = "search_python")]
+        topics.push("search_python".to_string());
+
+        let capabilities = Capabilities {
+            address: hex::encode(self.address()),
+            public_key: hex::encode(self.config.DKN_WALLET_PUBLIC_KEY.serialize_compressed()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            topics,
+            region: self.config.DKN_REGION.clone(),
+            busy: self.is_busy(),
+            degraded: self.outbox.is_degraded(),
+            timestamp: self.clock.now_nanos(),
+        };
+        let digest = sha256hash(capabilities.signing_bytes());
+
+        SignedCapabilities {
+            capabilities,
+            signature: self.sign_bytes(&digest),
+        }
+    }
+
+    /// Checks the payload size of a task message against the task size limits.
+    ///
+    /// Sizes are counted once for each task by [`DriaComputeNode::process_topic`], workers only read the outcome.
+    #[inline]
+    pub fn check_task_size(&self, message: &WakuMessage) -> TaskSize {
+        self.task_limits.check(message.payload_size())
+    }
+
     /// Creates the payload of a computation result, as per Dria Whitepaper section 5.1 algorithm 2:
     ///
     /// - Sign result with node `self.secret_key`
     /// - Encrypt `(signature || result)` with `task_public_key`
-    /// - Commit to `(signature || result)` using SHA256.
+    /// - Commit to `(signature || result)` using the configured hash algorithm.
+    ///
+    /// Digests are computed with the algorithm given by `DKN_HASH_ALGORITHM`, which defaults to SHA256, and the
+    /// algorithm is tagged in the payload unless it is the default.
     pub fn create_payload(
         &self,
         result: impl AsRef<[u8]>,
         task_pubkey: &[u8],
     ) -> NodeResult<TaskResponsePayload> {
+        let hash = self.config.DKN_HASH_ALGORITHM;
+
         // sign result
-        let result_digest: [u8; 32] = sha256hash(result.as_ref());
+        let result_digest: [u8; 32] = hash.digest(result.as_ref());
         let result_msg = Message::parse(&result_digest);
-        let (signature, recid) = sign(&result_msg, &self.config.DKN_WALLET_SECRET_KEY);
+        let (signature, recid) = timed("sign", || {
+            sign(&result_msg, &self.config.DKN_WALLET_SECRET_KEY)
+        });
         let signature: [u8; 64] = signature.serialize();
         let recid: [u8; 1] = [recid.serialize()];
 
         // encrypt result
-        let ciphertext = encrypt(task_pubkey, result.as_ref())?;
+        let ciphertext = timed("encrypt", || encrypt(task_pubkey, result.as_ref()))?;
 
         // concatenate `signature_bytes` and `digest_bytes`
         let mut preimage = Vec::new();
         preimage.extend_from_slice(&signature);
         preimage.extend_from_slice(&recid);
         preimage.extend_from_slice(&result_digest);
-        let commitment: [u8; 32] = sha256hash(preimage);
+        let commitment: [u8; 32] = hash.digest(preimage);
 
         Ok(TaskResponsePayload {
             commitment: hex::encode(commitment),
             ciphertext: hex::encode(ciphertext),
             signature: format!("{}{}", hex::encode(signature), hex::encode(recid)),
+            hash,
+            region: None,
+            region_signature: None,
         })
     }
 
+    /// Acknowledges the region of this node in the result payload of a task with region constraints,
+    /// by signing the digest of `task_id || region`, computed with the hash algorithm of the payload. Does nothing if the task has no region constraints.
+    pub fn acknowledge_region(
+        &self,
+        payload: &mut TaskResponsePayload,
+        task_id: &TaskId,
+        regions: &[String],
+    ) {
+        if regions.is_empty() {
+            return;
+        }
+
+        if let Some(region) = &self.config.DKN_REGION {
+            let digest = payload
+                .hash
+                .digest([task_id.as_bytes(), region.as_bytes()].concat());
+            payload.region = Some(region.clone());
+            payload.region_signature = Some(self.sign_bytes(&digest));
+        }
+    }
+
     /// Subscribe to a certain task with its topic.
     pub async fn subscribe_topic(&self, topic: &str) {
-        let content_topic = WakuMessage::create_content_topic(topic);
+        let content_topic = self.network.content_topic(topic);
 
         const MAX_RETRIES: usize = 30;
         let mut retry_count = 0; // retry count for edge case
         while let Err(e) = self.waku.relay.subscribe(&content_topic).await {
-            if retry_count < MAX_RETRIES {
+            // a relay that is starting up may reject requests for a while, so only fatal errors abort early
+            if !e.kind.is_fatal() && retry_count < MAX_RETRIES {
                 log::error!(
                     "Error subscribing to {}: {}\nRetrying in 5 seconds ({}/{}).",
                     topic,
@@ -145,6 +380,7 @@ impl DriaComputeNode {
             } else {
                 log::error!("Error subscribing to {}: {}\nAborting.", topic, e);
                 self.cancellation.cancel();
+                return;
             }
         }
 
@@ -153,7 +389,7 @@ impl DriaComputeNode {
 
     /// Unsubscribe from a certain task with its topic.
     pub async fn unsubscribe_topic(&self, topic: &str) -> NodeResult<()> {
-        let content_topic = WakuMessage::create_content_topic(topic);
+        let content_topic = self.network.content_topic(topic);
         self.waku.relay.unsubscribe(&content_topic).await?;
         log::info!("Unsubscribed from {}", topic);
         Ok(())
@@ -167,52 +403,309 @@ impl DriaComputeNode {
     /// Send a message via Waku Relay on a topic, where
     /// the topic is subscribed, the message is sent, and
     /// the topic is unsubscribed right afterwards.
+    ///
+    /// The message is published via the relay selected by [`WakuClient::select_relay`].
     pub async fn send_message_once(&self, message: WakuMessage) -> NodeResult<()> {
-        let content_topic = message.content_topic.clone();
-        self.waku.relay.subscribe(&content_topic).await?;
-        self.waku.relay.send_message(message).await?;
-        self.waku.relay.unsubscribe(&content_topic).await?;
+        self.waku.publisher().send_message_once(message).await
+    }
+
+    /// Queues a task result in the [`Outbox`], so that it is published by [`DriaComputeNode::publish_result`] without
+    /// waiting on the relay. Returns an error if the outbox is full, in which case the result is dropped.
+    ///
+    /// In dry-run, the message is written to a file named after its topic instead, and nothing is published.
+    pub async fn send_result(&self, message: WakuMessage) -> NodeResult<()> {
+        if let Some(dir) = &self.dry_run {
+            fs::create_dir_all(dir)?;
+            let path = dir.join(format!(
+                "{}-{}.json",
+                message.topic(&self.network).unwrap_or("unknown"),
+                message.timestamp
+            ));
+            fs::write(&path, serde_json::to_string_pretty(&message)?)?;
+            log::info!("Dry-run: wrote result to {}", path.display());
+            return Ok(());
+        }
+
+        self.outbox.push(message)
+    }
+
+    /// Publishes a task result via the selected relay like [`DriaComputeNode::send_message_once`], and mirrors it
+    /// to the mirror relays so that gateways in other regions receive it as well.
+    ///
+    /// The latency of publishing via the selected relay is recorded in the [`Outbox`], and mirroring is skipped
+    /// while publishing is degraded, so that the results themselves are not held back by the mirrors.
+    ///
+    /// Mirroring errors are logged only, and messages that are mirrors already are not mirrored again.
+    pub async fn publish_result(&self, message: WakuMessage) -> NodeResult<()> {
+        // the publisher is taken once, as the relay selection may change in the meantime
+        let publisher = self.waku.publisher();
+        let mirror = (!message.is_mirror()).then(|| message.to_mirror());
+        let started = Instant::now();
+        let res = publisher.send_message_once(message).await;
+        let latency = started.elapsed();
+        self.metrics.publish_latency(latency);
+        self.outbox.record_latency(latency);
+        if let Err(e) = res {
+            self.metrics.publish_failed(e.kind);
+            return Err(e);
+        }
+
+        if self.outbox.is_degraded() {
+            if mirror.is_some() && !self.waku.mirrors().is_empty() {
+                log::warn!("Publishing is degraded, skipping mirrors.");
+            }
+            return Ok(());
+        }
+
+        if let Some(mirror) = mirror {
+            let origin = publisher.get_base_url();
+            for relay in self.waku.mirrors() {
+                if relay.get_base_url() == origin {
+                    continue;
+                }
+
+                if let Err(e) = relay.send_message_once(mirror.clone()).await {
+                    log::error!("Error mirroring to {}: {}", relay.get_base_url(), e);
+                }
+            }
+        }
+
         Ok(())
     }
 
     /// Process messages on a certain topic, and if they are expected to be signed by the admin
     /// key of Dria, only keeps the ones that are authentic.
+    ///
+    /// On task topics, tasks over the hard size limit are dropped first, so that they are never decoded.
     pub async fn process_topic(&self, topic: &str, signed: bool) -> NodeResult<Vec<WakuMessage>> {
-        let content_topic = WakuMessage::create_content_topic(topic);
-        let mut messages: Vec<WakuMessage> = self.waku.relay.get_messages(&content_topic).await?;
+        let content_topic = self.network.content_topic(topic);
+        let messages: Vec<WakuMessage> = self.waku.relay.get_messages(&content_topic).await?;
 
         // dont bother if there are no messages
         if messages.is_empty() {
             return Ok(messages);
         }
 
+        Ok(self.filter_messages(topic, messages, signed))
+    }
+
+    /// Filters the messages received on a topic by their size and signature, see [`DriaComputeNode::process_topic`].
+    ///
+    /// Only messages that pass verification are counted as tasks, others over a size limit are counted as unverified.
+    fn filter_messages(
+        &self,
+        topic: &str,
+        mut messages: Vec<WakuMessage>,
+        signed: bool,
+    ) -> Vec<WakuMessage> {
+        // refuse tasks over the hard size limit before decoding or verifying them
+        let is_task_topic = TASK_TOPICS.contains(&topic);
+        if is_task_topic {
+            messages.retain(|message| {
+                if self.check_task_size(message) != TaskSize::Rejected {
+                    return true;
+                }
+
+                self.unverified_oversized.fetch_add(1, Ordering::Relaxed);
+                log::warn!(
+                    "Refusing message of {} bytes on {}, over the task size limit of {} bytes.",
+                    message.payload_size(),
+                    topic,
+                    self.task_limits.hard.unwrap_or_default()
+                );
+                false
+            });
+        }
+
         log::debug!("Received {} messages on topic {}:", messages.len(), topic);
         for message in &messages {
-            log::debug!("{}", message);
+            log::debug!("{} ({:?})", message, message.classify(&self.network));
         }
 
         // if signed, only keep messages that are authentic to Dria
         if signed {
             messages.retain(|message| {
-                message
+                let is_signed = message
                     .is_signed(&self.config.DKN_ADMIN_PUBLIC_KEY)
                     .unwrap_or_else(|e| {
                         log::warn!("Could not verify message signature: {}", e);
                         false
-                    })
+                    });
+                if !is_signed {
+                    self.signature_failures.fetch_add(1, Ordering::Relaxed);
+                    if is_task_topic && self.check_task_size(message) == TaskSize::Oversized {
+                        self.unverified_oversized.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                is_signed
             });
         }
 
-        Ok(messages)
+        // only tasks that passed verification are counted as such
+        if is_task_topic {
+            for message in &messages {
+                if self.check_task_size(message) == TaskSize::Oversized {
+                    self.oversized_tasks.fetch_add(1, Ordering::Relaxed);
+                    log::info!(
+                        "Deprioritizing task of {} bytes, over the soft limit of {} bytes.",
+                        message.payload_size(),
+                        self.task_limits.soft.unwrap_or_default()
+                    );
+                }
+            }
+        }
+
+        messages
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::crypto::HashAlgorithm;
     use ecies::decrypt;
     use libsecp256k1::{verify, PublicKey, SecretKey};
 
+    #[test]
+    fn test_filter_oversized_messages() {
+        let mut node = DriaComputeNode::default();
+        node.task_limits = TaskSizeLimits {
+            soft: Some(10),
+            hard: Some(100),
+        };
+        let messages = || {
+            [5, 50, 500]
+                .map(|size| WakuMessage::new(vec![b'a'; size], "synthesis"))
+                .to_vec()
+        };
+
+        // unsigned messages are not counted as tasks, whatever their size
+        assert!(node
+            .filter_messages("synthesis", messages(), true)
+            .is_empty());
+        assert_eq!(node.signature_failures.load(Ordering::Relaxed), 2);
+        assert_eq!(node.unverified_oversized.load(Ordering::Relaxed), 2);
+        assert_eq!(node.oversized_tasks.load(Ordering::Relaxed), 0);
+
+        // without verification, only those over the hard limit are refused
+        let filtered = node.filter_messages("synthesis", messages(), false);
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(node.unverified_oversized.load(Ordering::Relaxed), 3);
+        assert_eq!(node.oversized_tasks.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_result() {
+        let dir = std::env::temp_dir().join(format!(
+            "dkn-dry-run-{}",
+            crate::utils::get_current_time_nanos()
+        ));
+        let node = DriaComputeNode::default().with_dry_run(dir.clone());
+
+        // would fail if it tried to reach Waku
+        let message = WakuMessage::new(b"result", "81a63a34-96c6-4e5a-99b5-6b274d9de175");
+        node.send_result(message.clone())
+            .await
+            .expect("Should write result");
+
+        let path = dir.join(format!(
+            "81a63a34-96c6-4e5a-99b5-6b274d9de175-{}.json",
+            message.timestamp
+        ));
+        let written: WakuMessage =
+            serde_json::from_str(&fs::read_to_string(path).expect("Should read result"))
+                .expect("Should parse result");
+        assert_eq!(written.payload, message.payload);
+
+        fs::remove_dir_all(dir).expect("Should remove directory");
+    }
+
+    #[test]
+    fn test_signed_capabilities() {
+        let node = DriaComputeNode::default();
+        let signed = node.capabilities();
+        assert_eq!(signed.capabilities.address, hex::encode(node.address()));
+
+        // the timestamp is serialized like message timestamps, and the signature does not depend on the JSON
+        let json = serde_json::to_value(&signed).expect("Should serialize");
+        assert!(json["capabilities"]["timestamp"].is_i64());
+        let parsed: SignedCapabilities = serde_json::from_value(json).expect("Should deserialize");
+        assert_eq!(parsed, signed);
+
+        let rsv = hex::decode(&signed.signature).expect("Should decode");
+        let signature =
+            Signature::parse_standard_slice(&rsv[0..64]).expect("Should parse signature");
+        let recid = RecoveryId::parse(rsv[64]).expect("Should parse recovery id");
+        let message = Message::parse(&sha256hash(signed.capabilities.signing_bytes()));
+        assert_eq!(
+            libsecp256k1::recover(&message, &signature, &recid).expect("Should recover"),
+            node.config.DKN_WALLET_PUBLIC_KEY
+        );
+    }
+
+    #[test]
+    fn test_acknowledge_region() {
+        let mut node = DriaComputeNode::default();
+        node.config.DKN_REGION = Some("eu".to_string());
+        let task_id: TaskId = "81a63a34-96c6-4e5a-99b5-6b274d9de175"
+            .parse()
+            .expect("Should parse");
+
+        let mut payload = node
+            .create_payload(b"result", &node.config.DKN_WALLET_PUBLIC_KEY.serialize())
+            .expect("Should create payload");
+        node.acknowledge_region(&mut payload, &task_id, &[]);
+        assert!(payload.region.is_none() && payload.region_signature.is_none());
+
+        node.acknowledge_region(&mut payload, &task_id, &["eu".to_string()]);
+        assert_eq!(payload.region.as_deref(), Some("eu"));
+
+        let rsv =
+            hex::decode(payload.region_signature.expect("Should sign")).expect("Should decode");
+        let signature =
+            Signature::parse_standard_slice(&rsv[0..64]).expect("Should parse signature");
+        let recid = RecoveryId::parse(rsv[64]).expect("Should parse recovery id");
+        let message = Message::parse(&sha256hash([task_id.as_bytes(), b"eu".as_slice()].concat()));
+        assert_eq!(
+            libsecp256k1::recover(&message, &signature, &recid).expect("Should recover"),
+            node.config.DKN_WALLET_PUBLIC_KEY
+        );
+    }
+
+    #[test]
+    fn test_payload_hash_algorithm() {
+        let mut node = DriaComputeNode::default();
+        node.config.DKN_HASH_ALGORITHM = HashAlgorithm::Keccak256;
+        let public_key = node.config.DKN_WALLET_PUBLIC_KEY;
+
+        let payload = node
+            .create_payload(b"result", &public_key.serialize())
+            .expect("Should create payload");
+        let json = serde_json::to_value(&payload).expect("Should serialize");
+        assert_eq!(json["hash"], "keccak256");
+
+        // the verifier picks the algorithm from the tag
+        let parsed: TaskResponsePayload = serde_json::from_value(json).expect("Should parse");
+        let rsv = hex::decode(&parsed.signature).expect("Should decode");
+        let signature =
+            Signature::parse_standard_slice(&rsv[0..64]).expect("Should parse signature");
+        let message = Message::parse(&parsed.hash.digest(b"result"));
+        assert!(verify(&message, &signature, &public_key));
+
+        // untagged payloads are SHA256
+        let mut json = serde_json::to_value(
+            &node
+                .create_payload(b"result", &public_key.serialize())
+                .expect("Should create payload"),
+        )
+        .expect("Should serialize");
+        json.as_object_mut()
+            .expect("Should be object")
+            .remove("hash");
+        let parsed: TaskResponsePayload = serde_json::from_value(json).expect("Should parse");
+        assert_eq!(parsed.hash, HashAlgorithm::Sha256);
+    }
+
     /// This test demonstrates the creation and parsing of a payload.
     ///
     /// In DKN, the payload is created by Compute Node but parsed by the Admin Node.
@@ -0,0 +1,227 @@
+use serde_json::json;
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+use tokio::process::Command;
+
+use super::{get_current_time_nanos, units::env_duration};
+
+pub const DEFAULT_DKN_ALERT_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// How long an alert command may run before it is killed.
+const ALERT_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// # Alerter
+///
+/// Notifies the operator about critical conditions of the node, so that they can be paged without a monitoring stack.
+///
+/// An alert runs the command given with `DKN_ALERT_COMMAND` in a shell, with `DKN_ALERT_CONDITION` and `DKN_ALERT_MESSAGE`
+/// in its environment, and posts a JSON body to the webhook given with `DKN_ALERT_WEBHOOK`. Either or both can be given.
+/// The command is killed if it runs for longer than a minute.
+#[derive(Debug, Clone)]
+pub struct Alerter {
+    command: Option<String>,
+    webhook: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Alerter {
+    /// Creates an alerter from `DKN_ALERT_COMMAND` and `DKN_ALERT_WEBHOOK`.
+    ///
+    /// Returns `None` if neither is given, in which case alerts are only logged.
+    pub fn from_env() -> Option<Self> {
+        let read = |key: &str| {
+            env::var(key)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let command = read("DKN_ALERT_COMMAND");
+        let webhook = read("DKN_ALERT_WEBHOOK");
+        if command.is_none() && webhook.is_none() {
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|e| {
+                log::error!("Error building HTTP client: {}", e);
+                reqwest::Client::new()
+            });
+
+        Some(Self {
+            command,
+            webhook,
+            client,
+        })
+    }
+
+    /// Triggers an alert for the condition, errors are logged and otherwise ignored.
+    pub async fn alert(&self, address: &str, condition: &str, message: &str) {
+        if let Some(command) = &self.command {
+            match Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("DKN_ALERT_CONDITION", condition)
+                .env("DKN_ALERT_MESSAGE", message)
+                .kill_on_drop(true)
+                .spawn()
+            {
+                // the command is awaited in the background, so that a slow command does not block the caller
+                Ok(mut child) => {
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(ALERT_COMMAND_TIMEOUT, child.wait()).await {
+                            Ok(Ok(status)) if status.success() => {
+                                log::debug!("Alert command exited with {}", status)
+                            }
+                            Ok(Ok(status)) => log::warn!("Alert command exited with {}", status),
+                            Ok(Err(e)) => log::error!("Error waiting for alert command: {}", e),
+                            Err(_) => {
+                                log::error!(
+                                    "Alert command timed out after {}s, killing it.",
+                                    ALERT_COMMAND_TIMEOUT.as_secs()
+                                );
+                                if let Err(e) = child.kill().await {
+                                    log::error!("Error killing alert command: {}", e);
+                                }
+                            }
+                        }
+                    });
+                }
+                Err(e) => log::error!("Error running alert command: {}", e),
+            }
+        }
+
+        if let Some(webhook) = &self.webhook {
+            let body = json!({
+                "address": address,
+                "condition": condition,
+                "message": message,
+                "timestamp": get_current_time_nanos().to_string(),
+            });
+            let res = self
+                .client
+                .post(webhook)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|res| res.error_for_status());
+            if let Err(e) = res {
+                log::error!("Error calling alert webhook: {}", e);
+            }
+        }
+    }
+}
+
+/// Reads how long a condition must hold before it is alerted from `DKN_ALERT_AFTER`, e.g. `5m`.
+pub fn alert_after_from_env() -> Duration {
+    env_duration("DKN_ALERT_AFTER")
+        .unwrap_or_else(|e| panic!("Invalid configuration: {}", e))
+        .unwrap_or(DEFAULT_DKN_ALERT_AFTER)
+}
+
+/// A condition that is alerted once it has held for a while, e.g. the relay being unreachable for 5 minutes.
+#[derive(Debug, Clone)]
+pub struct AlertCondition {
+    pub name: &'static str,
+    after: Duration,
+    since: Option<Instant>,
+    alerted: bool,
+}
+
+impl AlertCondition {
+    pub fn new(name: &'static str, after: Duration) -> Self {
+        Self {
+            name,
+            after,
+            since: None,
+            alerted: false,
+        }
+    }
+
+    /// Records whether the condition holds at the given time.
+    ///
+    /// Returns `true` only once when the condition has held for long enough, and is reset when the condition clears.
+    pub fn update(&mut self, holds: bool, now: Instant) -> bool {
+        if !holds {
+            if self.alerted {
+                log::info!("Alert condition {} has cleared", self.name);
+            }
+            self.since = None;
+            self.alerted = false;
+            return false;
+        }
+
+        let since = *self.since.get_or_insert(now);
+        if !self.alerted && now.saturating_duration_since(since) >= self.after {
+            self.alerted = true;
+            return true;
+        }
+
+        false
+    }
+
+    /// Returns how long the condition has been holding.
+    pub fn duration(&self, now: Instant) -> Duration {
+        self.since
+            .map(|since| now.saturating_duration_since(since))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_condition() {
+        let start = Instant::now();
+        let mut condition = AlertCondition::new("test", Duration::from_secs(60));
+
+        assert!(!condition.update(true, start));
+        assert!(!condition.update(true, start + Duration::from_secs(30)));
+        assert!(condition.update(true, start + Duration::from_secs(60)));
+        assert_eq!(
+            condition.duration(start + Duration::from_secs(90)),
+            Duration::from_secs(90)
+        );
+
+        // alerted only once while it holds
+        assert!(!condition.update(true, start + Duration::from_secs(120)));
+
+        // clears and starts over
+        assert!(!condition.update(false, start + Duration::from_secs(150)));
+        assert_eq!(condition.duration(start), Duration::ZERO);
+        assert!(!condition.update(true, start + Duration::from_secs(180)));
+        assert!(condition.update(true, start + Duration::from_secs(240)));
+    }
+
+    #[tokio::test]
+    async fn test_alert_command() {
+        let path = env::temp_dir().join(format!("dkn-alert-{}", get_current_time_nanos()));
+        let alerter = Alerter {
+            command: Some(format!(
+                "echo \"$DKN_ALERT_CONDITION\" > {}",
+                path.display()
+            )),
+            webhook: None,
+            client: reqwest::Client::new(),
+        };
+        alerter.alert("address", "no_peers", "No peers").await;
+
+        // the command runs in the background
+        let mut output = String::new();
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            output = std::fs::read_to_string(&path).unwrap_or_default();
+            if !output.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(output.trim(), "no_peers");
+
+        std::fs::remove_file(path).expect("Should remove file");
+    }
+}
@@ -0,0 +1,429 @@
+use flate2::{write::GzEncoder, Compression};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashSet,
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    errors::NodeResult,
+    utils::{crypto::sha256hash, get_current_time_nanos, units::env_duration},
+};
+
+/// Number of entries after which an archive file is rotated.
+pub const DEFAULT_DKN_ARCHIVE_MAX_ENTRIES: usize = 1000;
+
+/// Fields that are redacted by default, which identify the requester and the selected nodes.
+pub const DEFAULT_DKN_ARCHIVE_REDACT: &str = "publicKey,filter";
+
+/// Name of the manifest file within the archive directory.
+const MANIFEST_FILE: &str = "manifest.jsonl";
+
+/// The kind of an archived entry.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveKind {
+    /// An inbound task.
+    Task,
+    /// An outbound result, before encryption.
+    Result,
+}
+
+/// # Archiver
+///
+/// An opt-in archiver that writes inbound tasks and outbound results to gzip-compressed JSON-lines files,
+/// so that operators can contribute them to research datasets.
+///
+/// Values of the redacted fields are replaced with their SHA256 digest, so that entries can still be joined
+/// without revealing the values. Files are rotated after a number of entries, and each finished file is recorded
+/// in `manifest.jsonl` along with its entry count and digest.
+///
+/// If a retention window is given, finished files older than the window are deleted by [`Archiver::purge`]. Only
+/// files with a record in the manifest are deleted, so that purging from another process (e.g. with `--purge`)
+/// never deletes the file that a running node is writing to.
+///
+/// Writes are synchronous and happen under a lock. An entry is a single line written to a buffered gzip encoder,
+/// so this is short enough to run within async workers; only rotation reads back and hashes a finished file,
+/// which is bounded by the number of entries per file. Purging scans the directory, and should be run with
+/// [`tokio::task::spawn_blocking`] from async code.
+pub struct Archiver {
+    dir: PathBuf,
+    redact: Vec<String>,
+    max_entries: usize,
+    retention: Option<Duration>,
+    current: Mutex<Option<ArchiveFile>>,
+}
+
+/// An archive file that is being written to.
+struct ArchiveFile {
+    name: String,
+    encoder: GzEncoder<File>,
+    entries: usize,
+}
+
+impl Archiver {
+    pub fn new(dir: PathBuf, redact: Vec<String>, max_entries: usize) -> Self {
+        Self {
+            dir,
+            redact,
+            max_entries: max_entries.max(1),
+            retention: None,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Sets the retention window, after which archive files are purged.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Creates an archiver from the environment, returns `None` if `DKN_ARCHIVE_DIR` is not set.
+    ///
+    /// Reads `DKN_ARCHIVE_REDACT` as a comma-separated list of fields and `DKN_ARCHIVE_MAX_ENTRIES`,
+    /// and defaults if not provided. Archives are kept forever unless `DKN_ARCHIVE_RETENTION` is given, e.g. `30d`.
+    pub fn from_env() -> Option<Self> {
+        let dir = env::var("DKN_ARCHIVE_DIR")
+            .ok()
+            .filter(|dir| !dir.is_empty())?;
+
+        let redact = env::var("DKN_ARCHIVE_REDACT")
+            .unwrap_or(DEFAULT_DKN_ARCHIVE_REDACT.to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        let max_entries = match env::var("DKN_ARCHIVE_MAX_ENTRIES") {
+            Ok(max_entries) if !max_entries.trim().is_empty() => {
+                max_entries.trim().parse::<usize>().unwrap_or_else(|e| {
+                    panic!("Invalid configuration: DKN_ARCHIVE_MAX_ENTRIES: {}", e)
+                })
+            }
+            _ => DEFAULT_DKN_ARCHIVE_MAX_ENTRIES,
+        };
+
+        let retention = env_duration("DKN_ARCHIVE_RETENTION")
+            .unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+
+        log::info!("Archive directory: {}", dir);
+        log::info!("Archive redacted fields: {}", redact.join(", "));
+
+        let archiver = Self::new(dir.into(), redact, max_entries);
+        match retention {
+            Some(retention) => {
+                log::info!("Archive retention: {:?}", retention);
+                Some(archiver.with_retention(retention))
+            }
+            None => Some(archiver),
+        }
+    }
+
+    /// Archives an entry with its redacted fields, rotating the archive file if it is full.
+    pub fn archive(&self, kind: ArchiveKind, body: &impl Serialize) -> NodeResult<()> {
+        let mut body = serde_json::to_value(body)?;
+        redact(&mut body, &self.redact);
+        let entry = json!({
+            "kind": kind,
+            "timestamp": get_current_time_nanos(),
+            "body": body,
+        });
+
+        let mut current = self.current.lock();
+        if current.is_none() {
+            *current = Some(self.create_file()?);
+        }
+
+        let file = current.as_mut().expect("Archive file should be open");
+        writeln!(file.encoder, "{}", entry)?;
+        file.entries += 1;
+
+        if file.entries >= self.max_entries {
+            if let Some(file) = current.take() {
+                self.finish_file(file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the current archive file, if any, and records it in the manifest.
+    pub fn flush(&self) -> NodeResult<()> {
+        let file = self.current.lock().take();
+        match file {
+            Some(file) => self.finish_file(file),
+            None => Ok(()),
+        }
+    }
+
+    /// Deletes the finished archive files that are older than the retention window, and removes them from the manifest.
+    ///
+    /// The age of a file is counted from its last modification, i.e. from when it was finished, so a file that was
+    /// open for a long time is kept for the whole window after rotation. Files without a record in the manifest have
+    /// not been finished, e.g. as they are being written to by this or another process, and are never deleted.
+    ///
+    /// Returns the number of deleted files, which is always zero without a retention window.
+    pub fn purge(&self) -> NodeResult<usize> {
+        let retention = match self.retention {
+            Some(retention) => retention,
+            None => return Ok(0),
+        };
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        // hold the lock so that the manifest is not written by this process meanwhile
+        let _current = self.current.lock();
+
+        // files are recorded in the manifest once they are finished, so an open file is never in it
+        let manifest_path = self.dir.join(MANIFEST_FILE);
+        let manifest = match manifest_path.exists() {
+            true => fs::read_to_string(&manifest_path)?,
+            false => String::new(),
+        };
+        let finished = manifest
+            .lines()
+            .filter_map(|line| {
+                let record = serde_json::from_str::<Value>(line).ok()?;
+                record["file"].as_str().map(String::from)
+            })
+            .collect::<HashSet<_>>();
+
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !finished.contains(&name) {
+                continue;
+            }
+
+            // files modified in the future are not expired
+            let age = entry
+                .metadata()?
+                .modified()?
+                .elapsed()
+                .unwrap_or(Duration::ZERO);
+            if age >= retention {
+                fs::remove_file(self.dir.join(&name))?;
+                purged += 1;
+            }
+        }
+
+        // keep the records of the remaining files in the manifest, re-read for those finished meanwhile
+        if purged > 0 {
+            let manifest = fs::read_to_string(&manifest_path)?
+                .lines()
+                .filter(|line| {
+                    serde_json::from_str::<Value>(line)
+                        .ok()
+                        .and_then(|record| record["file"].as_str().map(|f| self.dir.join(f)))
+                        .is_some_and(|path| path.exists())
+                })
+                .map(|line| format!("{}\n", line))
+                .collect::<String>();
+            fs::write(&manifest_path, manifest)?;
+            log::info!("Purged {} archive files", purged);
+        }
+
+        Ok(purged)
+    }
+
+    fn create_file(&self) -> NodeResult<ArchiveFile> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!("archive-{}.jsonl.gz", get_current_time_nanos());
+        let file = File::create(self.dir.join(&name))?;
+
+        Ok(ArchiveFile {
+            name,
+            encoder: GzEncoder::new(file, Compression::default()),
+            entries: 0,
+        })
+    }
+
+    fn finish_file(&self, file: ArchiveFile) -> NodeResult<()> {
+        file.encoder.finish()?;
+        let digest = sha256hash(fs::read(self.dir.join(&file.name))?);
+
+        let record = json!({
+            "file": file.name,
+            "entries": file.entries,
+            "sha256": hex::encode(digest),
+        });
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(MANIFEST_FILE))?;
+        writeln!(manifest, "{}", record)?;
+
+        log::info!("Archived {} entries to {}", file.entries, file.name);
+        Ok(())
+    }
+}
+
+impl Drop for Archiver {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("Error flushing archive: {}", e);
+        }
+    }
+}
+
+impl fmt::Debug for Archiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Archiver")
+            .field("dir", &self.dir)
+            .field("redact", &self.redact)
+            .field("max_entries", &self.max_entries)
+            .field("retention", &self.retention)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Replaces the values of the given fields with their hex-encoded SHA256 digest, recursively.
+fn redact(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if fields.contains(key) {
+                    *value = Value::String(hex::encode(sha256hash(value.to_string())));
+                } else {
+                    redact(value, fields);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                redact(value, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_redact() {
+        let mut value = json!({
+            "taskId": "task",
+            "publicKey": "secret",
+            "nested": [{ "publicKey": "secret" }],
+        });
+        redact(&mut value, &["publicKey".to_string()]);
+
+        let digest = hex::encode(sha256hash("\"secret\""));
+        assert_eq!(value["taskId"], "task");
+        assert_eq!(value["publicKey"], digest.as_str());
+        assert_eq!(value["nested"][0]["publicKey"], digest.as_str());
+    }
+
+    #[test]
+    fn test_archive_rotation() {
+        let dir = env::temp_dir().join(format!("dkn-archive-{}", get_current_time_nanos()));
+        let archiver = Archiver::new(dir.clone(), vec!["publicKey".to_string()], 2);
+        for i in 0..3 {
+            let body = json!({ "taskId": i, "publicKey": "secret" });
+            archiver
+                .archive(ArchiveKind::Task, &body)
+                .expect("Should archive");
+        }
+        archiver.flush().expect("Should flush");
+
+        // 3 entries with 2 entries per file makes 2 files
+        let manifest = fs::read_to_string(dir.join(MANIFEST_FILE)).expect("Should read manifest");
+        let records = manifest
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).expect("Should parse record"))
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["entries"], 2);
+        assert_eq!(records[1]["entries"], 1);
+
+        // entries are compressed and redacted
+        let name = records[0]["file"].as_str().expect("Should have file name");
+        let mut contents = String::new();
+        GzDecoder::new(File::open(dir.join(name)).expect("Should open file"))
+            .read_to_string(&mut contents)
+            .expect("Should decompress");
+        assert_eq!(contents.lines().count(), 2);
+        assert!(!contents.contains("secret"));
+
+        fs::remove_dir_all(dir).expect("Should remove directory");
+    }
+
+    #[test]
+    fn test_archive_purge() {
+        let dir = env::temp_dir().join(format!("dkn-archive-purge-{}", get_current_time_nanos()));
+        let archiver = Archiver::new(dir.clone(), vec![], 2);
+        for i in 0..3 {
+            archiver
+                .archive(ArchiveKind::Result, &json!({ "taskId": i }))
+                .expect("Should archive");
+        }
+
+        // nothing is purged without a retention window
+        assert_eq!(archiver.purge().expect("Should purge"), 0);
+
+        // the finished file is purged, but not the one being written to
+        let archiver = archiver.with_retention(Duration::ZERO);
+        assert_eq!(archiver.purge().expect("Should purge"), 1);
+        let manifest = fs::read_to_string(dir.join(MANIFEST_FILE)).expect("Should read manifest");
+        assert!(manifest.is_empty());
+
+        archiver.flush().expect("Should flush");
+        let manifest = fs::read_to_string(dir.join(MANIFEST_FILE)).expect("Should read manifest");
+        assert_eq!(manifest.lines().count(), 1);
+
+        fs::remove_dir_all(dir).expect("Should remove directory");
+    }
+
+    #[test]
+    fn test_archive_purge_by_finish_time() {
+        let dir = env::temp_dir().join(format!("dkn-archive-age-{}", get_current_time_nanos()));
+        fs::create_dir_all(&dir).expect("Should create directory");
+
+        // created at the epoch as per its name, but finished just now
+        let name = "archive-1.jsonl.gz";
+        fs::write(dir.join(name), b"").expect("Should write file");
+        let record = json!({ "file": name, "entries": 0, "sha256": "" });
+        fs::write(dir.join(MANIFEST_FILE), format!("{}\n", record)).expect("Should write manifest");
+
+        let archiver =
+            Archiver::new(dir.clone(), vec![], 2).with_retention(Duration::from_secs(3600));
+        assert_eq!(archiver.purge().expect("Should purge"), 0);
+        assert!(dir.join(name).exists());
+
+        fs::remove_dir_all(dir).expect("Should remove directory");
+    }
+
+    #[test]
+    fn test_archive_purge_other_process() {
+        let dir = env::temp_dir().join(format!("dkn-archive-other-{}", get_current_time_nanos()));
+
+        // a running node is writing to its current file, which has no record yet
+        let node = Archiver::new(dir.clone(), vec![], 2);
+        node.archive(ArchiveKind::Task, &json!({ "taskId": 0 }))
+            .expect("Should archive");
+
+        // purging from another process does not delete it, however old
+        let purger = Archiver::new(dir.clone(), vec![], 2).with_retention(Duration::ZERO);
+        assert_eq!(purger.purge().expect("Should purge"), 0);
+
+        // once finished, it can be purged
+        node.flush().expect("Should flush");
+        assert_eq!(purger.purge().expect("Should purge"), 1);
+
+        fs::remove_dir_all(dir).expect("Should remove directory");
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf, time::Duration};
+
+use crate::errors::NodeResult;
+
+/// # Capabilities
+///
+/// The current capabilities of a node, which is what external orchestration and marketplace listings need to know
+/// about the node without parsing relay traffic.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Wallet address of the node, hex-encoded.
+    pub address: String,
+    /// Compressed public key of the node, hex-encoded.
+    pub public_key: String,
+    /// Version of the node.
+    pub version: String,
+    /// Task topics that the node serves.
+    pub topics: Vec<String>,
+    /// Region of the node, if declared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Whether the node is busy with tasks.
+    pub busy: bool,
+    /// Whether publishing results is slow, see [`crate::waku::outbox::Outbox`].
+    pub degraded: bool,
+    /// Time at which the capabilities were taken in nanoseconds, serialized like message timestamps.
+    #[serde(with = "crate::waku::timestamp")]
+    pub timestamp: u128,
+}
+
+impl Capabilities {
+    /// Returns the bytes that the signature of the capabilities is computed over, which do not depend on how the
+    /// JSON document is serialized. These are the following lines, each ending with a newline:
+    ///
+    /// ```text
+    /// dria-capabilities/v1
+    /// address=<address>
+    /// publicKey=<public key>
+    /// version=<version>
+    /// topics=<comma-separated topics>
+    /// region=<region, or empty>
+    /// busy=<true or false>
+    /// degraded=<true or false>
+    /// timestamp=<timestamp in nanoseconds>
+    /// ```
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        format!(
+            "dria-capabilities/v1\naddress={}\npublicKey={}\nversion={}\ntopics={}\nregion={}\nbusy={}\ndegraded={}\ntimestamp={}\n",
+            self.address,
+            self.public_key,
+            self.version,
+            self.topics.join(","),
+            self.region.as_deref().unwrap_or_default(),
+            self.busy,
+            self.degraded,
+            self.timestamp
+        )
+        .into_bytes()
+    }
+}
+
+/// Capabilities along with a signature on the digest of their [`Capabilities::signing_bytes`], so that the document
+/// can be verified against the address of the node.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignedCapabilities {
+    pub capabilities: Capabilities,
+    /// A 65-byte RSV signature on the SHA256 digest of [`Capabilities::signing_bytes`], hex-encoded.
+    pub signature: String,
+}
+
+/// # Capabilities Exporter
+///
+/// Writes the signed capabilities of the node to the file given with `DKN_CAPABILITIES_FILE`, and posts them to
+/// the endpoint given with `DKN_CAPABILITIES_URL`, periodically.
+#[derive(Debug, Clone)]
+pub struct CapabilitiesExporter {
+    file: Option<PathBuf>,
+    url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl CapabilitiesExporter {
+    /// Creates an exporter from the environment, returns `None` if neither a file nor an endpoint is given.
+    pub fn from_env() -> Option<Self> {
+        let read = |key: &str| {
+            env::var(key)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let file = read("DKN_CAPABILITIES_FILE").map(PathBuf::from);
+        let url = read("DKN_CAPABILITIES_URL");
+        if file.is_none() && url.is_none() {
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|e| {
+                log::error!("Error building HTTP client: {}", e);
+                reqwest::Client::new()
+            });
+
+        Some(Self { file, url, client })
+    }
+
+    /// Exports the capabilities to the file and the endpoint.
+    ///
+    /// The file is replaced atomically, so that readers never see a partially written document.
+    pub async fn export(&self, capabilities: &SignedCapabilities) -> NodeResult<()> {
+        if let Some(file) = &self.file {
+            let tmp = file.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string_pretty(capabilities)?)?;
+            fs::rename(&tmp, file)?;
+        }
+
+        if let Some(url) = &self.url {
+            self.client
+                .post(url)
+                .json(capabilities)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Debug;
+
+use super::get_current_time_nanos;
+#[cfg(any(test, feature = "test_utils"))]
+use super::saturating::add_duration;
+
+/// A source of the current time, so that time-dependent logic such as deadlines can be tested
+/// with a controllable clock instead of the system clock.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time in nanoseconds since the Unix epoch.
+    fn now_nanos(&self) -> u128;
+}
+
+/// The system clock, see [`get_current_time_nanos`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now_nanos(&self) -> u128 {
+        get_current_time_nanos()
+    }
+}
+
+/// A clock that only moves when it is told to, for tests.
+///
+/// Clones share the same time, so a clock given to the node can be advanced from the test.
+#[cfg(any(test, feature = "test_utils"))]
+#[derive(Debug, Default, Clone)]
+pub struct TestClock {
+    nanos: std::sync::Arc<parking_lot::Mutex<u128>>,
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl TestClock {
+    pub fn new(nanos: u128) -> Self {
+        Self {
+            nanos: std::sync::Arc::new(parking_lot::Mutex::new(nanos)),
+        }
+    }
+
+    /// Sets the current time in nanoseconds.
+    pub fn set(&self, nanos: u128) {
+        *self.nanos.lock() = nanos;
+    }
+
+    /// Moves the current time forward.
+    pub fn advance(&self, duration: std::time::Duration) {
+        let mut nanos = self.nanos.lock();
+        *nanos = add_duration(*nanos, duration);
+    }
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl Clock for TestClock {
+    fn now_nanos(&self) -> u128 {
+        *self.nanos.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_test_clock() {
+        let clock = TestClock::new(1_000);
+        let shared = clock.clone();
+        assert_eq!(clock.now_nanos(), 1_000);
+
+        shared.advance(Duration::from_micros(1));
+        assert_eq!(clock.now_nanos(), 2_000);
+
+        shared.set(0);
+        assert_eq!(clock.now_nanos(), 0);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let before = get_current_time_nanos();
+        let now = SystemClock.now_nanos();
+        assert!(now >= before);
+    }
+}
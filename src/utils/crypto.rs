@@ -1,17 +1,71 @@
 use ecies::PublicKey;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sha3::Keccak256;
+use std::{fmt, str::FromStr};
+
+use super::timing::timed;
 
 /// Generic SHA256 function.
 #[inline]
 pub fn sha256hash(data: impl AsRef<[u8]>) -> [u8; 32] {
-    Sha256::digest(data).into()
+    timed("sha256", || Sha256::digest(data).into())
 }
 
 /// Generic KECCAK256 function.
 #[inline]
 pub fn keccak256hash(data: impl AsRef<[u8]>) -> [u8; 32] {
-    Keccak256::digest(data).into()
+    timed("keccak256", || Keccak256::digest(data).into())
+}
+
+/// # Hash Algorithm
+///
+/// A digest algorithm that is tagged in payloads, so that future protocol versions can change the algorithm
+/// while payloads of earlier versions can still be verified. Payloads without a tag use SHA256.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// Computes the digest of the data with this algorithm.
+    #[inline]
+    pub fn digest(&self, data: impl AsRef<[u8]>) -> [u8; 32] {
+        match self {
+            HashAlgorithm::Sha256 => sha256hash(data),
+            HashAlgorithm::Keccak256 => keccak256hash(data),
+        }
+    }
+
+    /// Returns `true` for the default algorithm, which is not tagged in payloads.
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        *self == HashAlgorithm::default()
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha256 => write!(f, "sha256"),
+            HashAlgorithm::Keccak256 => write!(f, "keccak256"),
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "keccak256" => Ok(HashAlgorithm::Keccak256),
+            _ => Err(format!("unknown hash algorithm {:?}", s)),
+        }
+    }
 }
 
 /// Given a secp256k1 public key, finds the corresponding Ethereum address.
@@ -44,6 +98,21 @@ mod tests {
         assert_eq!(sha256hash(MESSAGE), expected.as_slice());
     }
 
+    #[test]
+    fn test_hash_algorithm() {
+        assert_eq!(HashAlgorithm::Sha256.digest(MESSAGE), sha256hash(MESSAGE));
+        assert_eq!(
+            HashAlgorithm::Keccak256.digest(MESSAGE),
+            keccak256hash(MESSAGE)
+        );
+
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Keccak256] {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert_eq!("KECCAK256".parse(), Ok(HashAlgorithm::Keccak256));
+        assert!("blake3".parse::<HashAlgorithm>().is_err());
+    }
+
     #[test]
     fn test_address() {
         let sk = SecretKey::parse_slice(DUMMY_KEY).expect("Should parse key.");
@@ -0,0 +1,193 @@
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    Client,
+};
+use serde::Deserialize;
+use std::{
+    env,
+    error::Error,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::Duration,
+};
+
+/// DNS record type for IPv4 addresses.
+const RECORD_TYPE_A: u16 = 1;
+
+/// DNS record type for IPv6 addresses.
+const RECORD_TYPE_AAAA: u16 = 28;
+
+/// Timeout of a DoH query, after which the query fails and the system DNS is used instead.
+const DOH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// # DNS-over-HTTPS Resolver
+///
+/// Resolves hosts using the JSON API of a DoH server, such as `https://cloudflare-dns.com/dns-query`
+/// or `https://dns.google/resolve`. If the query fails or returns no addresses, which is the case for
+/// local hosts such as Docker services, it falls back to the system DNS.
+///
+/// Queries time out after 2 seconds, so an unresponsive DoH server falls back to the system DNS as well.
+///
+/// The DoH server itself is resolved with the system DNS.
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    url: String,
+    client: Client,
+    stats: Arc<DohStats>,
+}
+
+/// Counters of a [`DohResolver`].
+#[derive(Debug, Default)]
+pub struct DohStats {
+    /// Number of hosts resolved via DoH.
+    pub resolved: AtomicU64,
+    /// Number of hosts resolved via system DNS.
+    pub fallbacks: AtomicU64,
+    /// Number of failed DoH queries.
+    pub failures: AtomicU64,
+}
+
+#[derive(Deserialize, Debug)]
+struct DohResponse {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+impl DohResolver {
+    pub fn new(url: String) -> Self {
+        let client = Client::builder()
+            .timeout(DOH_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                log::error!("Error building DoH client: {}", e);
+                Client::new()
+            });
+
+        Self {
+            url,
+            client,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Returns the resolver shared by all HTTP clients of the node, if `DKN_DOH_URL` is provided.
+    pub fn shared() -> Option<Arc<DohResolver>> {
+        static RESOLVER: OnceLock<Option<Arc<DohResolver>>> = OnceLock::new();
+        RESOLVER
+            .get_or_init(|| {
+                let url = env::var("DKN_DOH_URL").ok().filter(|url| !url.is_empty())?;
+                log::info!("DoH URL: {}", url);
+                Some(Arc::new(DohResolver::new(url)))
+            })
+            .clone()
+    }
+
+    /// Returns the URL of the DoH server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the counters of this resolver.
+    pub fn stats(&self) -> &DohStats {
+        &self.stats
+    }
+
+    /// Queries the A and AAAA records of a host.
+    async fn query(&self, host: &str) -> Result<Vec<IpAddr>, reqwest::Error> {
+        let mut addrs = Vec::new();
+        for record_type in ["A", "AAAA"] {
+            let res: DohResponse = self
+                .client
+                .get(&self.url)
+                .query(&[("name", host), ("type", record_type)])
+                .header("Accept", "application/dns-json")
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            addrs.extend(parse_answers(res));
+        }
+
+        Ok(addrs)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(resolve(self.clone(), name))
+    }
+}
+
+async fn resolve(resolver: DohResolver, name: Name) -> Result<Addrs, Box<dyn Error + Send + Sync>> {
+    let host = name.as_str();
+    match resolver.query(host).await {
+        Ok(ips) if !ips.is_empty() => {
+            resolver.stats.resolved.fetch_add(1, Ordering::Relaxed);
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            return Ok(addrs);
+        }
+        Ok(_) => log::debug!("DoH found no addresses for {}", host),
+        Err(e) => {
+            resolver.stats.failures.fetch_add(1, Ordering::Relaxed);
+            log::warn!("DoH query for {} failed: {}", host, e);
+        }
+    }
+
+    resolver.stats.fallbacks.fetch_add(1, Ordering::Relaxed);
+    let addrs: Addrs = Box::new(tokio::net::lookup_host((host, 0)).await?);
+    Ok(addrs)
+}
+
+/// Returns the addresses within A and AAAA records of a DoH response, skipping others such as CNAME.
+fn parse_answers(res: DohResponse) -> Vec<IpAddr> {
+    res.answer
+        .into_iter()
+        .filter(|answer| {
+            answer.record_type == RECORD_TYPE_A || answer.record_type == RECORD_TYPE_AAAA
+        })
+        .filter_map(|answer| answer.data.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_answers() {
+        let res: DohResponse = serde_json::from_str(
+            r#"{
+                "Status": 0,
+                "Answer": [
+                    { "name": "example.com", "type": 5, "TTL": 300, "data": "cdn.example.com." },
+                    { "name": "cdn.example.com", "type": 1, "TTL": 300, "data": "93.184.215.14" },
+                    { "name": "cdn.example.com", "type": 28, "TTL": 300, "data": "2606:2800:21f:cb07:6820:80da:af6b:8b2c" }
+                ]
+            }"#,
+        )
+        .expect("Should parse response");
+
+        let addrs = parse_answers(res);
+        assert_eq!(addrs.len(), 2);
+        assert_eq!(addrs[0], "93.184.215.14".parse::<IpAddr>().unwrap());
+        assert!(addrs[1].is_ipv6());
+    }
+
+    #[test]
+    fn test_parse_no_answers() {
+        let res: DohResponse =
+            serde_json::from_str(r#"{ "Status": 3 }"#).expect("Should parse response");
+        assert!(parse_answers(res).is_empty());
+    }
+}
@@ -1,5 +1,40 @@
 use reqwest::Client;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+use super::{doh::DohResolver, units::env_duration};
+
+/// Connection and request timeouts of a [`BaseClient`], no timeout is applied when `None`.
+///
+/// Hosts with both IPv4 and IPv6 addresses are dialed with Happy Eyeballs by the underlying connector,
+/// so a slow IPv6 path falls back to IPv4 instead of waiting for the connection timeout.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HttpTimeouts {
+    /// Timeout for establishing a connection.
+    pub connect: Option<Duration>,
+    /// Timeout for the entire request, from connecting until the response body is read.
+    pub request: Option<Duration>,
+}
+
+impl HttpTimeouts {
+    /// Reads the timeouts of a client from `<PREFIX>_CONNECT_TIMEOUT` and `<PREFIX>_TIMEOUT`,
+    /// falling back to the global `DKN_HTTP_CONNECT_TIMEOUT` and `DKN_HTTP_TIMEOUT`.
+    ///
+    /// Timeouts are given as durations such as `30s` or `5m`, see [`env_duration`].
+    pub fn from_env(prefix: &str) -> Self {
+        let read = |name: &str| {
+            let timeout = match env_duration(&format!("{}_{}", prefix, name)) {
+                Ok(None) => env_duration(&format!("DKN_HTTP_{}", name)),
+                timeout => timeout,
+            };
+            timeout.unwrap_or_else(|e| panic!("Invalid configuration: {}", e))
+        };
+
+        Self {
+            connect: read("CONNECT_TIMEOUT"),
+            request: read("TIMEOUT"),
+        }
+    }
+}
 
 /// A wrapper for GET, POST and DELETE requests.
 #[derive(Debug, Clone)]
@@ -9,8 +44,30 @@ pub struct BaseClient {
 }
 
 impl BaseClient {
+    /// Creates a new client for the given base URL, with the global timeouts.
     pub fn new(url: String) -> Self {
-        let client = Client::new();
+        Self::with_timeouts(url, HttpTimeouts::from_env("DKN_HTTP"))
+    }
+
+    /// Creates a new client for the given base URL, with the given timeouts.
+    ///
+    /// If a DNS-over-HTTPS resolver is configured with `DKN_DOH_URL`, it is used for resolving hosts.
+    pub fn with_timeouts(url: String, timeouts: HttpTimeouts) -> Self {
+        let mut builder = Client::builder();
+        if let Some(resolver) = DohResolver::shared() {
+            builder = builder.dns_resolver(resolver);
+        }
+        if let Some(connect) = timeouts.connect {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(request) = timeouts.request {
+            builder = builder.timeout(request);
+        }
+
+        let client = builder.build().unwrap_or_else(|e| {
+            log::error!("Error building HTTP client: {}", e);
+            Client::new()
+        });
         BaseClient {
             base_url: url,
             client,
@@ -96,6 +153,7 @@ fn convert_to_query_params(params: HashMap<String, String>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
 
     #[test]
     fn test_convert_to_query_params() {
@@ -107,4 +165,22 @@ mod tests {
         let expected = "key1=v_a+lue%2F1".to_string();
         assert_eq!(convert_to_query_params(params), expected);
     }
+
+    #[test]
+    fn test_timeouts_from_env() {
+        env::set_var("DKN_HTTP_CONNECT_TIMEOUT", "10");
+        env::set_var("DKN_HTTP_TIMEOUT", "1m");
+        env::set_var("TEST_PROVIDER_TIMEOUT", "5s");
+        env::set_var("TEST_PROVIDER_CONNECT_TIMEOUT", "");
+
+        // provider overrides the request timeout, and uses the global connection timeout
+        let timeouts = HttpTimeouts::from_env("TEST_PROVIDER");
+        assert_eq!(timeouts.connect, Some(Duration::from_secs(10)));
+        assert_eq!(timeouts.request, Some(Duration::from_secs(5)));
+
+        env::remove_var("DKN_HTTP_CONNECT_TIMEOUT");
+        env::remove_var("DKN_HTTP_TIMEOUT");
+        env::remove_var("TEST_PROVIDER_TIMEOUT");
+        env::remove_var("TEST_PROVIDER_CONNECT_TIMEOUT");
+    }
 }
@@ -0,0 +1,121 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::errors::ErrorKind;
+
+/// # Node Metrics
+///
+/// Typed metrics recorded by the workers and the node, so that they do not depend on a specific metrics backend.
+/// The node uses [`NoopMetrics`] unless another backend is given with `DriaComputeNode::with_metrics`.
+pub trait NodeMetrics: Debug + Send + Sync {
+    /// A task on the given topic has started.
+    fn task_started(&self, topic: &str);
+
+    /// A task on the given topic has completed, and its result was queued for publishing.
+    fn task_completed(&self, topic: &str);
+
+    /// Publishing a message has failed with the given kind of error.
+    fn publish_failed(&self, kind: ErrorKind);
+
+    /// A result was published, or failed to be, after the given latency.
+    fn publish_latency(&self, latency: Duration);
+
+    /// A compute provider such as Ollama has responded after the given latency.
+    fn provider_latency(&self, provider: &str, latency: Duration);
+}
+
+/// Metrics backend that discards everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl NodeMetrics for NoopMetrics {
+    fn task_started(&self, _: &str) {}
+    fn task_completed(&self, _: &str) {}
+    fn publish_failed(&self, _: ErrorKind) {}
+    fn publish_latency(&self, _: Duration) {}
+    fn provider_latency(&self, _: &str, _: Duration) {}
+}
+
+/// A recorded metric, see [`InMemoryMetrics`].
+#[cfg(any(test, feature = "test_utils"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Metric {
+    TaskStarted(String),
+    TaskCompleted(String),
+    PublishFailed(ErrorKind),
+    PublishLatency(Duration),
+    ProviderLatency(String, Duration),
+}
+
+/// Metrics backend that records everything in memory, so that tests can assert on the emitted metrics.
+///
+/// Clones share the same records, so metrics given to the node can be read from the test.
+#[cfg(any(test, feature = "test_utils"))]
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryMetrics {
+    metrics: std::sync::Arc<parking_lot::Mutex<Vec<Metric>>>,
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl InMemoryMetrics {
+    /// Returns the recorded metrics, in order.
+    pub fn recorded(&self) -> Vec<Metric> {
+        self.metrics.lock().clone()
+    }
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl NodeMetrics for InMemoryMetrics {
+    fn task_started(&self, topic: &str) {
+        self.metrics
+            .lock()
+            .push(Metric::TaskStarted(topic.to_string()));
+    }
+
+    fn task_completed(&self, topic: &str) {
+        self.metrics
+            .lock()
+            .push(Metric::TaskCompleted(topic.to_string()));
+    }
+
+    fn publish_failed(&self, kind: ErrorKind) {
+        self.metrics.lock().push(Metric::PublishFailed(kind));
+    }
+
+    fn publish_latency(&self, latency: Duration) {
+        self.metrics.lock().push(Metric::PublishLatency(latency));
+    }
+
+    fn provider_latency(&self, provider: &str, latency: Duration) {
+        self.metrics
+            .lock()
+            .push(Metric::ProviderLatency(provider.to_string(), latency));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_in_memory_metrics() {
+        let metrics = InMemoryMetrics::default();
+        let backend: Arc<dyn NodeMetrics> = Arc::new(metrics.clone());
+
+        backend.task_started("synthesis");
+        backend.provider_latency("ollama", Duration::from_millis(250));
+        backend.publish_failed(ErrorKind::Transient);
+        backend.publish_latency(Duration::from_secs(1));
+        NoopMetrics.task_completed("synthesis");
+
+        assert_eq!(
+            metrics.recorded(),
+            vec![
+                Metric::TaskStarted("synthesis".to_string()),
+                Metric::ProviderLatency("ollama".to_string(), Duration::from_millis(250)),
+                Metric::PublishFailed(ErrorKind::Transient),
+                Metric::PublishLatency(Duration::from_secs(1)),
+            ]
+        );
+    }
+}
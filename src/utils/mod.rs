@@ -1,6 +1,15 @@
+pub mod alert;
+pub mod archive;
+pub mod capabilities;
+pub mod clock;
 pub mod crypto;
+pub mod doh;
 pub mod filter;
 pub mod http;
+pub mod metrics;
+pub mod saturating;
+pub mod timing;
+pub mod units;
 
 use std::time::{Duration, SystemTime};
 use tokio::signal::unix::{signal, SignalKind};
@@ -21,12 +30,15 @@ pub fn get_current_time_nanos() -> u128 {
 }
 
 /// Waits for SIGTERM or SIGINT, and cancels the given token when the signal is received.
+///
+/// Returns early if the token is cancelled elsewhere, e.g. due to a panic.
 pub async fn wait_for_termination(cancellation: CancellationToken) -> std::io::Result<()> {
     let mut sigterm = signal(SignalKind::terminate())?; // Docker sends SIGTERM
     let mut sigint = signal(SignalKind::interrupt())?; // Ctrl+C sends SIGINT
     tokio::select! {
         _ = sigterm.recv() => log::warn!("Recieved SIGTERM"),
         _ = sigint.recv() => log::warn!("Recieved SIGINT"),
+        _ = cancellation.cancelled() => log::warn!("Cancelled"),
     };
 
     cancellation.cancel();
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// Converts nanoseconds to `u64`, saturating at `u64::MAX`, e.g. for [`Duration::from_nanos`].
+#[inline]
+pub fn nanos_to_u64(nanos: u128) -> u64 {
+    u64::try_from(nanos).unwrap_or(u64::MAX)
+}
+
+/// Returns the time from `earlier` to `later` given in nanoseconds, which is zero if `later` is before `earlier`.
+#[inline]
+pub fn elapsed_nanos(earlier: u128, later: u128) -> Duration {
+    Duration::from_nanos(nanos_to_u64(later.saturating_sub(earlier)))
+}
+
+/// Adds a duration to a timestamp in nanoseconds, saturating at `u128::MAX`.
+#[inline]
+pub fn add_duration(timestamp: u128, duration: Duration) -> u128 {
+    timestamp.saturating_add(duration.as_nanos())
+}
+
+/// Subtracts a duration from a timestamp in nanoseconds, saturating at zero.
+#[inline]
+pub fn sub_duration(timestamp: u128, duration: Duration) -> u128 {
+    timestamp.saturating_sub(duration.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nanos_to_u64() {
+        assert_eq!(nanos_to_u64(0), 0);
+        assert_eq!(nanos_to_u64(u64::MAX as u128), u64::MAX);
+        assert_eq!(nanos_to_u64(u64::MAX as u128 + 1), u64::MAX);
+        assert_eq!(nanos_to_u64(u128::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_elapsed_nanos() {
+        assert_eq!(elapsed_nanos(1_000, 3_000), Duration::from_nanos(2_000));
+        assert_eq!(elapsed_nanos(3_000, 1_000), Duration::ZERO);
+        assert_eq!(elapsed_nanos(u128::MAX, 0), Duration::ZERO);
+        assert_eq!(elapsed_nanos(0, u128::MAX), Duration::from_nanos(u64::MAX));
+    }
+
+    #[test]
+    fn test_add_sub_duration() {
+        let second = Duration::from_secs(1);
+        assert_eq!(add_duration(0, second), 1_000_000_000);
+        assert_eq!(add_duration(u128::MAX, second), u128::MAX);
+        assert_eq!(
+            add_duration(u128::MAX - 1, Duration::from_nanos(1)),
+            u128::MAX
+        );
+
+        assert_eq!(sub_duration(1_000_000_000, second), 0);
+        assert_eq!(sub_duration(0, second), 0);
+        assert_eq!(sub_duration(1, Duration::MAX), 0);
+        assert_eq!(
+            sub_duration(u128::MAX, Duration::MAX),
+            u128::MAX - Duration::MAX.as_nanos()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+/// Runs a crypto operation such as `sha256` or `verify`, and records how long it took when the `crypto_timing`
+/// feature is enabled. Without the feature, this is the same as calling `f`.
+#[inline(always)]
+pub fn timed<T>(op: &'static str, f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "crypto_timing")]
+    {
+        let started = std::time::Instant::now();
+        let output = f();
+        enabled::record(op, started.elapsed());
+        output
+    }
+
+    #[cfg(not(feature = "crypto_timing"))]
+    {
+        let _ = op;
+        f()
+    }
+}
+
+#[cfg(feature = "crypto_timing")]
+pub use enabled::*;
+
+#[cfg(feature = "crypto_timing")]
+mod enabled {
+    use parking_lot::Mutex;
+    use std::{
+        collections::BTreeMap,
+        env,
+        fs::{File, OpenOptions},
+        io::Write,
+        sync::OnceLock,
+        time::Duration,
+    };
+
+    /// Number of histogram buckets, the bucket `i` counts durations in `[2^(i-1), 2^i)` nanoseconds.
+    const BUCKETS: usize = 64;
+
+    /// # Histogram
+    ///
+    /// A histogram of the durations of an operation, with power-of-two buckets in nanoseconds.
+    #[derive(Debug, Clone)]
+    pub struct Histogram {
+        pub count: u64,
+        pub total: Duration,
+        pub max: Duration,
+        buckets: [u64; BUCKETS],
+    }
+
+    impl Default for Histogram {
+        fn default() -> Self {
+            Self {
+                count: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+                buckets: [0; BUCKETS],
+            }
+        }
+    }
+
+    impl Histogram {
+        pub fn record(&mut self, duration: Duration) {
+            let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+            let bucket = (u64::BITS - nanos.leading_zeros()) as usize;
+            self.buckets[bucket.min(BUCKETS - 1)] += 1;
+            self.count += 1;
+            self.total = self.total.saturating_add(duration);
+            self.max = self.max.max(duration);
+        }
+
+        /// Returns the mean duration.
+        pub fn mean(&self) -> Duration {
+            match u32::try_from(self.count) {
+                Ok(0) => Duration::ZERO,
+                Ok(count) => self.total / count,
+                Err(_) => Duration::from_nanos(
+                    u64::try_from(self.total.as_nanos() / self.count as u128).unwrap_or(u64::MAX),
+                ),
+            }
+        }
+
+        /// Returns an upper bound of the given quantile such as `0.99`, at the resolution of the buckets.
+        pub fn quantile(&self, quantile: f64) -> Duration {
+            let target = (self.count as f64 * quantile).ceil() as u64;
+            let mut seen = 0;
+            for (bucket, count) in self.buckets.iter().enumerate() {
+                seen += count;
+                if seen >= target.max(1) {
+                    return Duration::from_nanos(
+                        1u64.checked_shl(bucket as u32).unwrap_or(u64::MAX),
+                    );
+                }
+            }
+            self.max
+        }
+    }
+
+    fn histograms() -> &'static Mutex<BTreeMap<&'static str, Histogram>> {
+        static HISTOGRAMS: OnceLock<Mutex<BTreeMap<&'static str, Histogram>>> = OnceLock::new();
+        HISTOGRAMS.get_or_init(Default::default)
+    }
+
+    /// The trace file given with `DKN_CRYPTO_TRACE_FILE`, where each operation is appended as a line in the folded
+    /// stack format of flamegraph tools, i.e. `crypto;<op> <nanos>`.
+    fn trace_file() -> Option<&'static Mutex<File>> {
+        static TRACE_FILE: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+        TRACE_FILE
+            .get_or_init(|| {
+                let path = env::var("DKN_CRYPTO_TRACE_FILE").ok()?;
+                match OpenOptions::new().create(true).append(true).open(&path) {
+                    Ok(file) => Some(Mutex::new(file)),
+                    Err(e) => {
+                        log::error!("Could not open crypto trace file {}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+
+    pub(super) fn record(op: &'static str, duration: Duration) {
+        histograms().lock().entry(op).or_default().record(duration);
+
+        if let Some(file) = trace_file() {
+            let _ = writeln!(file.lock(), "crypto;{} {}", op, duration.as_nanos());
+        }
+    }
+
+    /// Returns the histograms of all recorded operations, by their name.
+    pub fn snapshot() -> BTreeMap<&'static str, Histogram> {
+        histograms().lock().clone()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_histogram() {
+            let mut histogram = Histogram::default();
+            for micros in [1, 2, 3, 100] {
+                histogram.record(Duration::from_micros(micros));
+            }
+            assert_eq!(histogram.count, 4);
+            assert_eq!(histogram.max, Duration::from_micros(100));
+            assert_eq!(histogram.mean(), Duration::from_nanos(26_500));
+
+            // 2us falls into [1024, 2048) ns, and 3us into [2048, 4096) ns
+            assert_eq!(histogram.quantile(0.5), Duration::from_nanos(2048));
+            assert_eq!(histogram.quantile(0.75), Duration::from_nanos(4096));
+            assert!(histogram.quantile(1.0) >= histogram.max);
+        }
+
+        #[test]
+        fn test_timed() {
+            let output = super::super::timed("test", || 42);
+            assert_eq!(output, 42);
+            assert_eq!(snapshot().get("test").map(|h| h.count), Some(1));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Deserializer};
+use std::{env, time::Duration};
+
+use crate::errors::NodeResult;
+
+/// Parses a human-friendly duration such as `500ms`, `30s`, `5m`, `2h` or `1d`.
+///
+/// A number without a unit is read as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = split_unit(value);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration {:?}", value))?;
+
+    let secs = match unit.to_lowercase().as_str() {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit {:?} in {:?}", unit, value)),
+    };
+
+    number
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration {:?} is too large", value))
+}
+
+/// Parses a human-friendly size such as `512B`, `150KB`, `10MiB` or `1GB` into bytes.
+///
+/// A number without a unit is read as bytes. Both decimal (`KB`) and binary (`KiB`) units are supported.
+pub fn parse_size(value: &str) -> Result<usize, String> {
+    let (number, unit) = split_unit(value);
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("invalid size {:?}", value))?;
+
+    let bytes = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "kib" => 1 << 10,
+        "mb" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "gb" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        _ => return Err(format!("invalid size unit {:?} in {:?}", unit, value)),
+    };
+
+    number
+        .checked_mul(bytes)
+        .ok_or_else(|| format!("size {:?} is too large", value))
+}
+
+/// Splits a value into its leading number and the unit that follows.
+fn split_unit(value: &str) -> (&str, &str) {
+    let value = value.trim();
+    let index = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(index);
+    (number, unit.trim())
+}
+
+/// Reads a duration from the environment, see [`parse_duration`].
+///
+/// Returns `None` if the variable is not set or empty, and an error naming the variable if it is invalid.
+pub fn env_duration(key: &str) -> NodeResult<Option<Duration>> {
+    match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => parse_duration(&value)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", key, e).into()),
+        _ => Ok(None),
+    }
+}
+
+/// Reads a size from the environment, see [`parse_size`].
+///
+/// Returns `None` if the variable is not set or empty, and an error naming the variable if it is invalid.
+pub fn env_size(key: &str) -> NodeResult<Option<usize>> {
+    match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => parse_size(&value)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", key, e).into()),
+        _ => Ok(None),
+    }
+}
+
+/// A value that is given either as a string or as a plain number.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(u64),
+}
+
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            StringOrNumber::String(value) => value,
+            StringOrNumber::Number(value) => value.to_string(),
+        }
+    }
+}
+
+/// Deserializes a duration with [`parse_duration`], to be used with `#[serde(deserialize_with = "...")]`.
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    let value = StringOrNumber::deserialize(deserializer)?.into_string();
+    parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+/// Deserializes a size with [`parse_size`], to be used with `#[serde(deserialize_with = "...")]`.
+pub fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = StringOrNumber::deserialize(deserializer)?.into_string();
+    parse_size(&value).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration(" 5 m "), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2H"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("-5s").is_err());
+        assert!(parse_duration("5 minutes").is_err());
+        assert!(parse_duration(&format!("{}d", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("512B"), Ok(512));
+        assert_eq!(parse_size("150KB"), Ok(150_000));
+        assert_eq!(parse_size("150KiB"), Ok(153_600));
+        assert_eq!(parse_size("10mb"), Ok(10_000_000));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+
+        assert!(parse_size("").is_err());
+        assert!(parse_size("1.5MB").is_err());
+        assert!(parse_size("10XB").is_err());
+    }
+
+    #[test]
+    fn test_env_errors_name_key() {
+        env::set_var("TEST_UNITS_DURATION", "soon");
+        let err = env_duration("TEST_UNITS_DURATION").expect_err("Should fail");
+        assert!(err.to_string().contains("TEST_UNITS_DURATION"));
+
+        env::set_var("TEST_UNITS_SIZE", "");
+        assert_eq!(env_size("TEST_UNITS_SIZE").expect("Should parse"), None);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(deserialize_with = "deserialize_duration")]
+            timeout: Duration,
+            #[serde(deserialize_with = "deserialize_size")]
+            max_size: usize,
+        }
+
+        let config: Config = serde_json::from_str(r#"{ "timeout": "5m", "max_size": 1024 }"#)
+            .expect("Should deserialize");
+        assert_eq!(config.timeout, Duration::from_secs(300));
+        assert_eq!(config.max_size, 1024);
+
+        let err = serde_json::from_str::<Config>(r#"{ "timeout": "soon", "max_size": 1 }"#)
+            .err()
+            .expect("Should fail");
+        assert!(err.to_string().contains("invalid duration"));
+    }
+}
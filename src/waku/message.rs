@@ -1,12 +1,20 @@
 use crate::{
+    compute::{payload::TaskResponsePayload, task_id::TaskId},
     errors::NodeResult,
-    utils::{crypto::sha256hash, get_current_time_nanos},
+    utils::{
+        clock::{Clock, SystemClock},
+        crypto::sha256hash,
+        timing::timed,
+    },
 };
 
+use super::{network::NetworkConfig, relay::DEFAULT_DKN_WAKU_MAX_MESSAGE_SIZE};
+
 use base64::{prelude::BASE64_STANDARD, Engine};
 use core::fmt;
 use ecies::PublicKey;
 use serde::{Deserialize, Serialize};
+use std::{env, sync::OnceLock};
 
 /// Within Waku Message and Content Topic we specify version to be 0 since
 ///  encryption takes place at our application layer, instead of at protocol layer of Waku.
@@ -23,6 +31,12 @@ pub const WAKU_APP_NAME: &str = "dria";
 /// So it makes sense to have messages be ephemeral.
 pub const WAKU_EPHEMERAL: bool = true;
 
+/// Topics on which tasks are given.
+pub const TASK_TOPICS: [&str; 2] = ["synthesis", "search_python"];
+
+/// Meta value for messages that are mirrored to other relays, so that they are not mirrored again.
+pub const WAKU_MIRROR_META: &[u8] = b"dria-mirror";
+
 /// A Waku message, as defined by [14/WAKU2-MESSAGE](https://github.com/vacp2p/rfc-index/blob/main/waku/standards/core/14/message.md).
 ///
 /// ## Fields
@@ -31,8 +45,16 @@ pub const WAKU_EPHEMERAL: bool = true;
 /// - `content_topic`: The message content topic for optional content-based filtering.
 /// - `version`: Message version. Used to indicate type of payload encryption. Default version is 0 (no payload encryption).
 /// - `timestamp`: The time at which the message is generated by its sender. This field holds the Unix epoch time in nanoseconds as a 64-bits integer value.
+/// It is kept as `u128` within the node, and is converted with an overflow check when serialized, see [`super::timestamp`].
 /// - `ephemeral`: This flag indicates the transient nature of the message. Indicates if the message is eligible to be stored by the STORE protocol.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// - `meta`: Optional base64 encoded metadata, used to mark mirrored messages.
+///
+/// ## Display
+///
+/// Payloads may contain user queries, so by default a message is displayed & debugged with the length and
+/// digest of its payload only. The full payload is displayed with the alternate flag, e.g. `{:#}`, or everywhere
+/// when `DKN_LOG_PAYLOADS` is set to `true`.
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WakuMessage {
     pub payload: String,
@@ -40,10 +62,31 @@ pub struct WakuMessage {
     #[serde(default)]
     pub version: u8,
     #[serde(default)]
+    #[serde(with = "super::timestamp")]
     pub timestamp: u128,
     #[serde(default)]
     #[serde(skip_serializing)] // see: https://github.com/waku-org/nwaku/issues/2643
     pub ephemeral: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<String>,
+}
+
+/// The kind of a message, see [`WakuMessage::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// A task given on one of the [`TASK_TOPICS`].
+    Task,
+    /// A task result, given on the topic of its task id.
+    Result,
+    /// A heartbeat request.
+    Heartbeat,
+    /// A control message, given on the `control` topic.
+    Control,
+    /// A signature in response to a heartbeat, given on the topic of the heartbeat uuid.
+    Ack,
+    /// A message that is not recognized, possibly of another app.
+    Unknown,
 }
 
 /// 65-byte signature as hex characters take up 130 characters.
@@ -62,18 +105,66 @@ impl WakuMessage {
     /// - `topic` is the name of the topic itself within the full content topic. The rest of the content topic
     /// is filled in automatically, e.g. `/dria/0/<topic>/proto`.
     pub fn new(payload: impl AsRef<[u8]>, topic: &str) -> Self {
+        Self::new_with_clock(payload, topic, &SystemClock)
+    }
+
+    /// Creates a new ephemeral Waku message with the current timestamp of the given clock, version 0.
+    pub fn new_with_clock(payload: impl AsRef<[u8]>, topic: &str, clock: &dyn Clock) -> Self {
+        Self::new_with_network(payload, topic, &NetworkConfig::default(), clock)
+    }
+
+    /// Creates a new ephemeral Waku message within the given network, with the current timestamp of the given clock.
+    pub fn new_with_network(
+        payload: impl AsRef<[u8]>,
+        topic: &str,
+        network: &NetworkConfig,
+        clock: &dyn Clock,
+    ) -> Self {
         WakuMessage {
             payload: BASE64_STANDARD.encode(payload),
-            content_topic: Self::create_content_topic(topic).to_string(),
+            content_topic: network.content_topic(topic),
             version: WAKU_ENC_VERSION,
-            timestamp: get_current_time_nanos(),
+            timestamp: clock.now_nanos(),
             ephemeral: WAKU_EPHEMERAL,
+            meta: None,
+        }
+    }
+
+    /// Creates a builder for a message with non-default fields, see [`WakuMessageBuilder`].
+    pub fn builder(payload: impl AsRef<[u8]>, topic: &str) -> WakuMessageBuilder {
+        WakuMessageBuilder::new(payload, topic)
+    }
+
+    /// Returns a copy of this message that is marked as a mirror.
+    pub fn to_mirror(&self) -> Self {
+        WakuMessage {
+            meta: Some(BASE64_STANDARD.encode(WAKU_MIRROR_META)),
+            ..self.clone()
         }
     }
 
+    /// Returns `true` if this message is a mirrored copy, which must not be mirrored again.
+    pub fn is_mirror(&self) -> bool {
+        self.meta
+            .as_ref()
+            .and_then(|meta| BASE64_STANDARD.decode(meta).ok())
+            .is_some_and(|meta| meta == WAKU_MIRROR_META)
+    }
+
+    /// Returns the size of the decoded payload in bytes, without decoding it.
+    pub fn payload_size(&self) -> usize {
+        let padding = self
+            .payload
+            .bytes()
+            .rev()
+            .take_while(|b| *b == b'=')
+            .count();
+        (self.payload.len() / 4 * 3).saturating_sub(padding)
+    }
+
     /// Decodes the base64 payload into bytes.
     pub fn decode_payload(&self) -> Result<Vec<u8>, base64::DecodeError> {
-        BASE64_STANDARD.decode(&self.payload)
+        timed("base64_decode", || BASE64_STANDARD.decode(&self.payload))
     }
 
     /// Decodes and parses the payload into JSON.
@@ -82,7 +173,9 @@ impl WakuMessage {
 
         let body = if signed {
             // skips the 65 byte hex signature
-            &payload[SIGNATURE_SIZE..]
+            payload
+                .get(SIGNATURE_SIZE..)
+                .ok_or("Payload is shorter than a signature")?
         } else {
             &payload[..]
         };
@@ -96,14 +189,69 @@ impl WakuMessage {
         let payload = self.decode_payload()?;
 
         // parse signature (64 bytes = 128 hex chars, although the full 65-byte RSV signature is given)
+        if payload.len() < SIGNATURE_SIZE {
+            return Err("Payload is shorter than a signature".into());
+        }
         let (signature, body) = (&payload[..SIGNATURE_SIZE - 2], &payload[SIGNATURE_SIZE..]);
-        let signature = hex::decode(signature).expect("could not decode");
-        let signature =
-            libsecp256k1::Signature::parse_standard_slice(&signature).expect("could not parse");
+        let signature = hex::decode(signature)?;
+        let signature = libsecp256k1::Signature::parse_standard_slice(&signature)
+            .map_err(|e| format!("Could not parse signature: {:?}", e))?;
 
         // verify signature
         let digest = libsecp256k1::Message::parse(&sha256hash(body));
-        Ok(libsecp256k1::verify(&digest, &signature, public_key))
+        Ok(timed("verify", || {
+            libsecp256k1::verify(&digest, &signature, public_key)
+        }))
+    }
+
+    /// Returns the topic within the content topic, see [`NetworkConfig::topic`].
+    ///
+    /// Returns `None` if the content topic does not belong to the given network.
+    pub fn topic(&self, network: &NetworkConfig) -> Option<&str> {
+        network.topic(&self.content_topic)
+    }
+
+    /// Classifies the message by its content topic within the given network, and for messages on task id topics,
+    /// by their payload.
+    pub fn classify(&self, network: &NetworkConfig) -> MessageKind {
+        let topic = match self.topic(network) {
+            Some(topic) => topic,
+            None => return MessageKind::Unknown,
+        };
+
+        match topic {
+            "heartbeat" => MessageKind::Heartbeat,
+            "control" => MessageKind::Control,
+            topic if TASK_TOPICS.contains(&topic) => MessageKind::Task,
+            topic if topic.parse::<TaskId>().is_ok() => {
+                let payload = match self.decode_payload() {
+                    Ok(payload) => payload,
+                    Err(_) => return MessageKind::Unknown,
+                };
+
+                if payload.len() == SIGNATURE_SIZE && payload.iter().all(u8::is_ascii_hexdigit) {
+                    MessageKind::Ack
+                } else if serde_json::from_slice::<TaskResponsePayload>(&payload).is_ok() {
+                    MessageKind::Result
+                } else {
+                    MessageKind::Unknown
+                }
+            }
+            _ => MessageKind::Unknown,
+        }
+    }
+
+    /// Returns a redacted description of the payload, with its length and the prefix of its digest.
+    fn redacted_payload(&self) -> String {
+        let payload_decoded = self
+            .decode_payload()
+            .unwrap_or(self.payload.as_bytes().to_vec());
+
+        format!(
+            "<{} bytes, sha256:{}>",
+            payload_decoded.len(),
+            hex::encode(&sha256hash(&payload_decoded)[..8])
+        )
     }
 
     /// A [Content Topic](https://docs.waku.org/learn/concepts/content-topics) is represented as a string with the form:
@@ -113,18 +261,163 @@ impl WakuMessage {
     /// /waku/2/default-waku/proto # example
     /// ```
     ///
-    /// `app-name` defaults to `dria` unless specified otherwise with the second argument.
+    /// `app-name` is `dria` here, see [`NetworkConfig::content_topic`] for other networks.
     #[inline]
     pub fn create_content_topic(topic: &str) -> String {
-        format!(
-            "/{}/{}/{}/{}",
-            WAKU_APP_NAME, WAKU_ENC_VERSION, topic, WAKU_ENCODING
-        )
+        NetworkConfig::default().content_topic(topic)
+    }
+}
+
+/// # Waku Message Builder
+///
+/// Builds a [`WakuMessage`] with non-default fields, see [`WakuMessage::builder`]. Unlike [`WakuMessage::new`],
+/// the topic and payload are validated when the message is built:
+///
+/// - `topic` must be non-empty, and consist of alphanumerics, `-`, `_` and `.` only.
+/// - `timestamp` must fit in an `i64`, as it is sent to the relay as such, see [`super::timestamp`].
+/// - the message as sent to the relay, i.e. serialized as JSON with its base64-encoded payload, must not be larger
+/// than the maximum message size, which defaults to the relay limit.
+#[derive(Debug, Clone)]
+pub struct WakuMessageBuilder {
+    payload: Vec<u8>,
+    topic: String,
+    version: u8,
+    timestamp: Option<u128>,
+    ephemeral: bool,
+    meta: Option<Vec<u8>>,
+    max_message_size: usize,
+    network: NetworkConfig,
+}
+
+impl WakuMessageBuilder {
+    pub fn new(payload: impl AsRef<[u8]>, topic: &str) -> Self {
+        WakuMessageBuilder {
+            payload: payload.as_ref().to_vec(),
+            topic: topic.to_string(),
+            version: WAKU_ENC_VERSION,
+            timestamp: None,
+            ephemeral: WAKU_EPHEMERAL,
+            meta: None,
+            max_message_size: DEFAULT_DKN_WAKU_MAX_MESSAGE_SIZE,
+            network: NetworkConfig::default(),
+        }
+    }
+
+    /// Sets the topic, which is the name of the topic itself within the full content topic.
+    pub fn topic(mut self, topic: &str) -> Self {
+        self.topic = topic.to_string();
+        self
+    }
+
+    /// Sets the message version.
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the timestamp in nanoseconds, instead of the current time at build.
+    pub fn timestamp(mut self, timestamp: u128) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets whether the message is ephemeral.
+    pub fn ephemeral(mut self, ephemeral: bool) -> Self {
+        self.ephemeral = ephemeral;
+        self
+    }
+
+    /// Sets the metadata, which is base64 encoded internally.
+    pub fn meta(mut self, meta: impl AsRef<[u8]>) -> Self {
+        self.meta = Some(meta.as_ref().to_vec());
+        self
+    }
+
+    /// Sets the maximum size in bytes of the message as sent to the relay, which is about 4/3 of the payload size
+    /// due to base64 encoding.
+    pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets the network of the content topic.
+    pub fn network(mut self, network: &NetworkConfig) -> Self {
+        self.network = network.clone();
+        self
+    }
+
+    /// Validates the fields and builds the message, with the current time unless a timestamp is given.
+    pub fn build(self) -> NodeResult<WakuMessage> {
+        self.build_with_clock(&SystemClock)
     }
+
+    /// Validates the fields and builds the message, with the current time of the given clock unless a timestamp is given.
+    pub fn build_with_clock(self, clock: &dyn Clock) -> NodeResult<WakuMessage> {
+        if self.topic.is_empty() {
+            return Err("Topic must not be empty".into());
+        }
+        if let Some(c) = self
+            .topic
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        {
+            return Err(format!("Invalid character {:?} in topic {}", c, self.topic).into());
+        }
+
+        let timestamp = self.timestamp.unwrap_or_else(|| clock.now_nanos());
+        if super::timestamp::to_i64(timestamp).is_none() {
+            return Err(format!("Timestamp {} overflows i64", timestamp).into());
+        }
+
+        let message = WakuMessage {
+            payload: BASE64_STANDARD.encode(&self.payload),
+            content_topic: self.network.content_topic(&self.topic),
+            version: self.version,
+            timestamp,
+            ephemeral: self.ephemeral,
+            meta: self.meta.map(|meta| BASE64_STANDARD.encode(meta)),
+        };
+
+        // the relay limit applies to the message as it is sent, see `RelayClient::send_message`
+        let size = serde_json::to_string(&message)?.len();
+        if size > self.max_message_size {
+            return Err(format!(
+                "Message of {} bytes exceeds the limit of {} bytes",
+                size, self.max_message_size
+            )
+            .into());
+        }
+
+        Ok(message)
+    }
+}
+
+/// Returns `true` if full payloads should be logged, as given by `DKN_LOG_PAYLOADS`.
+fn log_payloads() -> bool {
+    static LOG_PAYLOADS: OnceLock<bool> = OnceLock::new();
+    *LOG_PAYLOADS.get_or_init(|| {
+        matches!(
+            env::var("DKN_LOG_PAYLOADS")
+                .unwrap_or_default()
+                .to_lowercase()
+                .as_str(),
+            "1" | "true" | "yes"
+        )
+    })
 }
 
 impl fmt::Display for WakuMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() && !log_payloads() {
+            return write!(
+                f,
+                "WakuMessage {} at {} {}",
+                self.content_topic,
+                self.timestamp,
+                self.redacted_payload()
+            );
+        }
+
         let payload_decoded = self
             .decode_payload()
             .unwrap_or(self.payload.as_bytes().to_vec());
@@ -138,6 +431,25 @@ impl fmt::Display for WakuMessage {
     }
 }
 
+impl fmt::Debug for WakuMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let payload = if log_payloads() {
+            self.payload.clone()
+        } else {
+            self.redacted_payload()
+        };
+
+        f.debug_struct("WakuMessage")
+            .field("payload", &payload)
+            .field("content_topic", &self.content_topic)
+            .field("version", &self.version)
+            .field("timestamp", &self.timestamp)
+            .field("ephemeral", &self.ephemeral)
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +482,157 @@ mod tests {
     fn test_display_message() {
         let message = WakuMessage::new(b"hello world", "test-topic");
         println!("{}", message);
+
+        // payload is redacted unless alternate flag is given
+        assert!(!format!("{}", message).contains("hello world"));
+        assert!(!format!("{:?}", message).contains(&message.payload));
+        assert!(format!("{}", message).contains("<11 bytes, sha256:b94d27b9934d3e08>"));
+        assert!(format!("{:#}", message).contains("hello world"));
+    }
+
+    #[test]
+    fn test_message_with_clock() {
+        let clock = crate::utils::clock::TestClock::new(1337);
+        let message = WakuMessage::new_with_clock(b"hello world", TOPIC, &clock);
+        assert_eq!(message.timestamp, 1337);
+    }
+
+    #[test]
+    fn test_message_builder() {
+        let message = WakuMessage::builder(b"hello world", TOPIC)
+            .version(1)
+            .timestamp(1337)
+            .ephemeral(false)
+            .meta(b"meta")
+            .build()
+            .expect("Should build");
+
+        assert_eq!(message.content_topic, "/dria/0/test-topic/proto");
+        assert_eq!(
+            message.decode_payload().expect("Should decode"),
+            b"hello world"
+        );
+        assert_eq!(message.version, 1);
+        assert_eq!(message.timestamp, 1337);
+        assert!(!message.ephemeral);
+        assert_eq!(message.meta, Some(BASE64_STANDARD.encode(b"meta")));
+
+        // without a timestamp, the time of the given clock is used
+        let clock = crate::utils::clock::TestClock::new(42);
+        let message = WakuMessage::builder(b"hello world", TOPIC)
+            .build_with_clock(&clock)
+            .expect("Should build");
+        assert_eq!(message.timestamp, 42);
+    }
+
+    #[test]
+    fn test_message_builder_validation() {
+        assert!(WakuMessage::builder(b"hello", "").build().is_err());
+        assert!(WakuMessage::builder(b"hello", "bad/topic").build().is_err());
+        assert!(WakuMessage::builder(b"hello", "bad topic").build().is_err());
+        assert!(WakuMessage::builder(b"hello", TOPIC)
+            .max_message_size(4)
+            .build()
+            .is_err());
+        assert!(WakuMessage::builder(b"hello", TOPIC)
+            .timestamp(u128::MAX)
+            .build()
+            .is_err());
+        assert!(WakuMessage::builder(b"hello", TOPIC)
+            .timestamp(i64::MAX as u128)
+            .build()
+            .is_ok());
+
+        // the limit applies to the encoded message, not the raw payload
+        let payload = vec![0u8; 120];
+        let size = serde_json::to_string(
+            &WakuMessage::builder(&payload, TOPIC)
+                .build()
+                .expect("Should build"),
+        )
+        .expect("Should serialize")
+        .len();
+        assert!(size > 4 * payload.len() / 3);
+        assert!(WakuMessage::builder(&payload, TOPIC)
+            .max_message_size(size - 1)
+            .build()
+            .is_err());
+        assert!(WakuMessage::builder(&payload, TOPIC)
+            .max_message_size(size)
+            .build()
+            .is_ok());
+
+        // uuid topics are used for heartbeats
+        assert!(
+            WakuMessage::builder(b"hello", "81a63a34-96c6-4e5a-99b5-6b274d9de175")
+                .build()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_classify_message() {
+        const UUID: &str = "81a63a34-96c6-4e5a-99b5-6b274d9de175";
+        let network = NetworkConfig::default();
+
+        let heartbeat = WakuMessage::new(b"{}", "heartbeat");
+        assert_eq!(heartbeat.topic(&network), Some("heartbeat"));
+        assert_eq!(heartbeat.classify(&network), MessageKind::Heartbeat);
+        assert_eq!(
+            WakuMessage::new(b"{}", "control").classify(&network),
+            MessageKind::Control
+        );
+        assert_eq!(
+            WakuMessage::new(b"{}", "synthesis").classify(&network),
+            MessageKind::Task
+        );
+
+        let ack = WakuMessage::new("ab".repeat(65), UUID);
+        assert_eq!(ack.classify(&network), MessageKind::Ack);
+
+        let result = TaskResponsePayload {
+            signature: "ab".repeat(65),
+            ciphertext: "cd".to_string(),
+            commitment: "ef".to_string(),
+            hash: Default::default(),
+            region: None,
+            region_signature: None,
+        };
+        let result = WakuMessage::new(result.to_string().expect("Should stringify"), UUID);
+        assert_eq!(result.classify(&network), MessageKind::Result);
+
+        assert_eq!(
+            WakuMessage::new(b"hello", UUID).classify(&network),
+            MessageKind::Unknown
+        );
+        assert_eq!(
+            WakuMessage::new(b"{}", TOPIC).classify(&network),
+            MessageKind::Unknown
+        );
+
+        let other_app = WakuMessage {
+            content_topic: "/waku/2/default-waku/proto".to_string(),
+            ..heartbeat
+        };
+        assert_eq!(other_app.topic(&network), None);
+        assert_eq!(other_app.classify(&network), MessageKind::Unknown);
+
+        // heartbeats of other networks are not classified as ours
+        let testnet = NetworkConfig::new("dria-testnet", 0).expect("Should create network");
+        let heartbeat = WakuMessage::new_with_network(b"{}", "heartbeat", &testnet, &SystemClock);
+        assert_eq!(heartbeat.classify(&network), MessageKind::Unknown);
+        assert_eq!(heartbeat.classify(&testnet), MessageKind::Heartbeat);
+    }
+
+    #[test]
+    fn test_mirror_message() {
+        let message = WakuMessage::new(b"hello world", TOPIC);
+        assert!(!message.is_mirror());
+
+        let mirror = message.to_mirror();
+        assert!(mirror.is_mirror());
+        assert_eq!(mirror.payload, message.payload);
+        assert_eq!(mirror.timestamp, message.timestamp);
     }
 
     #[test]
@@ -181,6 +644,7 @@ mod tests {
 
         // decode message
         let message_body = message.decode_payload().expect("Should decode");
+        assert_eq!(message.payload_size(), message_body.len());
         let body = serde_json::from_slice::<TestStruct>(&message_body).expect("Should deserialize");
         assert_eq!(
             serde_json::to_string(&body).expect("Should stringify"),
@@ -195,6 +659,23 @@ mod tests {
         assert_eq!(body, parsed_body);
     }
 
+    #[test]
+    fn test_short_signed_message() {
+        let sk = SecretKey::random(&mut thread_rng());
+        let pk = PublicKey::from_secret_key(&sk);
+
+        // a payload shorter than a signature is an error, not a panic
+        let message = WakuMessage::new(b"{}", TOPIC);
+        assert!(message.parse_payload::<serde_json::Value>(true).is_err());
+        assert!(message.is_signed(&pk).is_err());
+
+        // as is a signature of the right length that is not hex, or not a valid signature
+        for signature in ["x".repeat(SIGNATURE_SIZE), "f".repeat(SIGNATURE_SIZE)] {
+            let message = WakuMessage::new(format!("{}{{}}", signature), TOPIC);
+            assert!(message.is_signed(&pk).is_err());
+        }
+    }
+
     #[test]
     fn test_signed_message() {
         let mut rng = thread_rng();
@@ -1,13 +1,30 @@
 pub mod message;
+pub mod network;
+pub mod outbox;
 mod relay;
 
+pub mod timestamp;
+
 const DEFAULT_DKN_WAKU_URL: &str = "http://127.0.0.1:8645";
 
+/// Request timeout of the relays, unless `DKN_WAKU_TIMEOUT` or `DKN_HTTP_TIMEOUT` is given.
+const DEFAULT_DKN_WAKU_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of a relay health-check during relay selection, unless `DKN_WAKU_PROBE_TIMEOUT` is given.
+const DEFAULT_DKN_WAKU_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
 
 use crate::errors::NodeResult;
 
-use crate::utils::http::BaseClient;
+use crate::utils::{
+    http::{BaseClient, HttpTimeouts},
+    units::env_duration,
+};
 
 use self::relay::RelayClient;
 use serde::{Deserialize, Serialize};
@@ -17,6 +34,14 @@ use serde::{Deserialize, Serialize};
 pub struct WakuClient {
     base: BaseClient,
     pub relay: RelayClient,
+    /// All configured relays, the first one being the primary `relay`.
+    relays: Vec<RelayClient>,
+    /// Index of the relay within `relays` that is used for publishing.
+    selected: Arc<AtomicUsize>,
+    /// Relays that results are mirrored to, e.g. in other regions.
+    mirrors: Vec<RelayClient>,
+    /// Timeout of each relay health-check, see [`WakuClient::select_relay`].
+    probe_timeout: Duration,
 }
 
 impl Default for WakuClient {
@@ -27,15 +52,128 @@ impl Default for WakuClient {
 
 impl WakuClient {
     /// Creates a new instance of WakuClient.
+    ///
+    /// The URL can be a comma-separated list of relays, in which case the first one is the primary relay
+    /// used for subscriptions, and all of them are candidates for publishing.
+    ///
+    /// Mirror relays are read from `DKN_WAKU_MIRROR_URL` as a comma-separated list, if provided.
+    ///
+    /// Requests to the relays time out after 30 seconds by default, so that an unresponsive relay
+    /// does not block publishing forever.
     pub fn new(url: Option<String>) -> Self {
         let url: String = url.unwrap_or_else(|| {
             env::var("DKN_WAKU_URL").unwrap_or(DEFAULT_DKN_WAKU_URL.to_string())
         });
-        log::info!("Waku URL: {}", url);
-
-        let base = BaseClient::new(url);
+        let mut urls: Vec<String> = url
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(String::from)
+            .collect();
+        if urls.is_empty() {
+            urls.push(DEFAULT_DKN_WAKU_URL.to_string());
+        }
+        log::info!("Waku URL: {}", urls.join(", "));
+
+        let mut timeouts = HttpTimeouts::from_env("DKN_WAKU");
+        timeouts.request.get_or_insert(DEFAULT_DKN_WAKU_TIMEOUT);
+        let base = BaseClient::with_timeouts(urls[0].clone(), timeouts);
         let relay = RelayClient::new(base.clone());
-        WakuClient { base, relay }
+        let relays = std::iter::once(relay.clone())
+            .chain(
+                urls[1..]
+                    .iter()
+                    .map(|url| RelayClient::new(BaseClient::with_timeouts(url.clone(), timeouts))),
+            )
+            .collect();
+
+        let mirrors: Vec<RelayClient> = env::var("DKN_WAKU_MIRROR_URL")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| RelayClient::new(BaseClient::with_timeouts(url.to_string(), timeouts)))
+            .collect();
+        for mirror in &mirrors {
+            log::info!("Waku Mirror URL: {}", mirror.get_base_url());
+        }
+
+        let probe_timeout = env_duration("DKN_WAKU_PROBE_TIMEOUT")
+            .unwrap_or_else(|e| panic!("Invalid configuration: {}", e))
+            .unwrap_or(DEFAULT_DKN_WAKU_PROBE_TIMEOUT);
+
+        WakuClient {
+            base,
+            relay,
+            relays,
+            selected: Arc::new(AtomicUsize::new(0)),
+            mirrors,
+            probe_timeout,
+        }
+    }
+
+    /// Returns the relay used for publishing, see [`WakuClient::select_relay`].
+    pub fn publisher(&self) -> &RelayClient {
+        &self.relays[self.selected.load(Ordering::Relaxed)]
+    }
+
+    /// Returns the relays that results are mirrored to.
+    pub fn mirrors(&self) -> &[RelayClient] {
+        &self.mirrors
+    }
+
+    /// Probes all relays concurrently and selects the healthy one with the lowest round-trip time for publishing.
+    ///
+    /// Relays that do not respond within `DKN_WAKU_PROBE_TIMEOUT` (5 seconds by default) are considered unhealthy.
+    /// If none of the relays are healthy, the primary relay is selected. Does nothing if there is only one relay.
+    pub async fn select_relay(&self) {
+        if self.relays.len() == 1 {
+            return;
+        }
+
+        let mut probes = JoinSet::new();
+        for (index, relay) in self.relays.iter().enumerate() {
+            let relay = relay.clone();
+            let timeout = self.probe_timeout;
+            probes.spawn(async move { (index, relay.probe(timeout).await) });
+        }
+
+        let mut best: Option<(usize, Duration)> = None;
+        while let Some(probe) = probes.join_next().await {
+            let (index, result) = match probe {
+                Ok(probe) => probe,
+                Err(e) => {
+                    log::error!("Error probing relay: {}", e);
+                    continue;
+                }
+            };
+            let relay = &self.relays[index];
+            match result {
+                Ok(rtt) => {
+                    log::debug!(
+                        "Relay {} responded in {}ms",
+                        relay.get_base_url(),
+                        rtt.as_millis()
+                    );
+                    // ties go to the relay that comes first in the list, as probes finish in any order
+                    let is_better = match best {
+                        Some((best_index, best_rtt)) => {
+                            rtt < best_rtt || (rtt == best_rtt && index < best_index)
+                        }
+                        None => true,
+                    };
+                    if is_better {
+                        best = Some((index, rtt));
+                    }
+                }
+                Err(e) => log::warn!("Relay {} is not healthy: {}", relay.get_base_url(), e),
+            }
+        }
+
+        let index = best.map(|(index, _)| index).unwrap_or(0);
+        if self.selected.swap(index, Ordering::Relaxed) != index {
+            log::info!("Publishing via relay {}", self.relays[index].get_base_url());
+        }
     }
 
     /// Health-check for the node.
@@ -100,4 +238,15 @@ mod tests {
         let waku = WakuClient::new(None);
         assert_eq!(waku.base.get_base_url(), "im-a-host:1337");
     }
+
+    #[test]
+    fn test_waku_multiple_relays() {
+        let waku = WakuClient::new(Some("http://relay-a:8645, http://relay-b:8645".to_string()));
+        assert_eq!(waku.base.get_base_url(), "http://relay-a:8645");
+        assert_eq!(waku.relays.len(), 2);
+        assert_eq!(waku.relays[1].get_base_url(), "http://relay-b:8645");
+
+        // primary relay is used for publishing until a selection is made
+        assert_eq!(waku.publisher().get_base_url(), "http://relay-a:8645");
+    }
 }
@@ -0,0 +1,128 @@
+use std::env;
+
+use super::message::{WAKU_APP_NAME, WAKU_ENCODING, WAKU_ENC_VERSION};
+use crate::errors::NodeResult;
+
+/// # Network Config
+///
+/// The namespace of the content topics of a network, i.e. the `app-name` and `version` within
+/// `/app-name/version/topic/encoding`. Private deployments and testnets can use their own namespace
+/// on the same relays, without seeing the messages of other networks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkConfig {
+    app_name: String,
+    version: u8,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            app_name: WAKU_APP_NAME.to_string(),
+            version: WAKU_ENC_VERSION,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Creates a network config, the app name must be non-empty and consist of alphanumerics, `-`, `_` and `.` only.
+    pub fn new(app_name: &str, version: u8) -> NodeResult<Self> {
+        if app_name.is_empty() {
+            return Err("App name cannot be empty".into());
+        }
+        if let Some(c) = app_name
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        {
+            return Err(format!("Invalid character {:?} in app name {}", c, app_name).into());
+        }
+
+        Ok(Self {
+            app_name: app_name.to_string(),
+            version,
+        })
+    }
+
+    /// Reads `DKN_NETWORK_APP_NAME` and `DKN_NETWORK_VERSION` from the environment, and defaults to the Dria network if not provided.
+    pub fn from_env() -> Self {
+        let app_name = env::var("DKN_NETWORK_APP_NAME")
+            .ok()
+            .filter(|app_name| !app_name.trim().is_empty())
+            .unwrap_or(WAKU_APP_NAME.to_string());
+        let version = match env::var("DKN_NETWORK_VERSION") {
+            Ok(version) if !version.trim().is_empty() => version
+                .trim()
+                .parse::<u8>()
+                .unwrap_or_else(|e| panic!("Invalid configuration: DKN_NETWORK_VERSION: {}", e)),
+            _ => WAKU_ENC_VERSION,
+        };
+
+        let network = Self::new(app_name.trim(), version)
+            .unwrap_or_else(|e| panic!("Invalid configuration: DKN_NETWORK_APP_NAME: {}", e));
+        if network != Self::default() {
+            log::info!("Network: /{}/{}", network.app_name, network.version);
+        }
+
+        network
+    }
+
+    #[inline]
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Creates the content topic of a topic within this network, see [`super::message::WakuMessage::create_content_topic`].
+    pub fn content_topic(&self, topic: &str) -> String {
+        format!(
+            "/{}/{}/{}/{}",
+            self.app_name, self.version, topic, WAKU_ENCODING
+        )
+    }
+
+    /// Returns the topic within a content topic, i.e. the inverse of [`NetworkConfig::content_topic`].
+    ///
+    /// Returns `None` if the content topic does not belong to this network.
+    pub fn topic<'a>(&self, content_topic: &'a str) -> Option<&'a str> {
+        let prefix = format!("/{}/{}/", self.app_name, self.version);
+        let suffix = format!("/{}", WAKU_ENCODING);
+        content_topic
+            .strip_prefix(prefix.as_str())?
+            .strip_suffix(suffix.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_topics() {
+        let network = NetworkConfig::default();
+        assert_eq!(
+            network.content_topic("heartbeat"),
+            "/dria/0/heartbeat/proto"
+        );
+        assert_eq!(network.topic("/dria/0/heartbeat/proto"), Some("heartbeat"));
+
+        let testnet = NetworkConfig::new("dria-testnet", 1).expect("Should create network");
+        let content_topic = testnet.content_topic("heartbeat");
+        assert_eq!(content_topic, "/dria-testnet/1/heartbeat/proto");
+        assert_eq!(testnet.topic(&content_topic), Some("heartbeat"));
+
+        // networks do not see each other's topics
+        assert_eq!(network.topic(&content_topic), None);
+        assert_eq!(testnet.topic("/dria/0/heartbeat/proto"), None);
+    }
+
+    #[test]
+    fn test_network_validation() {
+        assert!(NetworkConfig::new("", 0).is_err());
+        assert!(NetworkConfig::new("dria/evil", 0).is_err());
+        assert!(NetworkConfig::new("my network", 0).is_err());
+        assert!(NetworkConfig::new("private.dria_1", 2).is_ok());
+    }
+}
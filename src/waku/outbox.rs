@@ -0,0 +1,177 @@
+use parking_lot::Mutex;
+use std::{
+    env,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+
+use crate::{errors::NodeResult, utils::units::env_duration};
+
+use super::message::WakuMessage;
+
+/// Number of results that can wait to be published, unless `DKN_OUTBOX_SIZE` is given.
+pub const DEFAULT_DKN_OUTBOX_SIZE: usize = 100;
+
+/// Publish latency over which publishing is considered degraded, unless `DKN_PUBLISH_SLOW_AFTER` is given.
+pub const DEFAULT_DKN_PUBLISH_SLOW_AFTER: Duration = Duration::from_secs(10);
+
+/// # Outbox
+///
+/// A bounded queue of results that are waiting to be published, so that task handlers do not wait on a slow
+/// relay. Results are published in order by the publish worker, and a result that does not fit in the queue is
+/// dropped with an error, as the relay is not keeping up anyway.
+///
+/// The latency of each publish is recorded, and publishing is flagged as degraded while it is over a threshold.
+/// Mirroring is shed while degraded, see `DriaComputeNode::publish_result`.
+#[derive(Debug)]
+pub struct Outbox {
+    sender: Sender<WakuMessage>,
+    receiver: Mutex<Option<Receiver<WakuMessage>>>,
+    capacity: usize,
+    slow_after: Duration,
+    degraded: AtomicBool,
+    dropped: AtomicU64,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self::new(DEFAULT_DKN_OUTBOX_SIZE, DEFAULT_DKN_PUBLISH_SLOW_AFTER)
+    }
+}
+
+impl Outbox {
+    pub fn new(capacity: usize, slow_after: Duration) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, receiver) = mpsc::channel(capacity);
+        Self {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            capacity,
+            slow_after,
+            degraded: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates an outbox with the size given by `DKN_OUTBOX_SIZE` and the latency threshold given by
+    /// `DKN_PUBLISH_SLOW_AFTER`, e.g. `10s`, and defaults if not provided.
+    pub fn from_env() -> Self {
+        let capacity = match env::var("DKN_OUTBOX_SIZE") {
+            Ok(size) if !size.trim().is_empty() => size
+                .trim()
+                .parse::<usize>()
+                .unwrap_or_else(|e| panic!("Invalid configuration: DKN_OUTBOX_SIZE: {}", e)),
+            _ => DEFAULT_DKN_OUTBOX_SIZE,
+        };
+        let slow_after = env_duration("DKN_PUBLISH_SLOW_AFTER")
+            .unwrap_or_else(|e| panic!("Invalid configuration: {}", e))
+            .unwrap_or(DEFAULT_DKN_PUBLISH_SLOW_AFTER);
+
+        Self::new(capacity, slow_after)
+    }
+
+    /// Queues a message to be published, or drops it if the outbox is full.
+    pub fn push(&self, message: WakuMessage) -> NodeResult<()> {
+        match self.sender.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(message)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Err(format!(
+                    "Outbox is full with {} messages, dropping {}",
+                    self.capacity, message
+                )
+                .into())
+            }
+            Err(TrySendError::Closed(_)) => Err("Outbox is closed".into()),
+        }
+    }
+
+    /// Takes the receiving end of the outbox, which can be done only once.
+    pub fn take_receiver(&self) -> Option<Receiver<WakuMessage>> {
+        self.receiver.lock().take()
+    }
+
+    /// Records the latency of a publish, and updates the degraded state with it.
+    pub fn record_latency(&self, latency: Duration) {
+        let degraded = latency > self.slow_after;
+        if self.degraded.swap(degraded, Ordering::Relaxed) != degraded {
+            if degraded {
+                log::warn!(
+                    "Publishing is degraded, took {}ms over the threshold of {}ms.",
+                    latency.as_millis(),
+                    self.slow_after.as_millis()
+                );
+            } else {
+                log::info!("Publishing has recovered.");
+            }
+        }
+    }
+
+    /// Returns `true` if the last publish was slower than the threshold.
+    #[inline]
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of messages that are waiting to be published.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.capacity - self.sender.capacity()
+    }
+
+    /// Returns `true` if no messages are waiting to be published.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages that were dropped as the outbox was full.
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbox_overflow() {
+        let outbox = Outbox::new(2, DEFAULT_DKN_PUBLISH_SLOW_AFTER);
+        for i in 0..2 {
+            outbox
+                .push(WakuMessage::new(format!("result-{}", i), "topic"))
+                .expect("Should queue");
+        }
+        assert_eq!(outbox.len(), 2);
+
+        // a full outbox drops the message instead of waiting
+        assert!(outbox.push(WakuMessage::new("result-2", "topic")).is_err());
+        assert_eq!(outbox.dropped(), 1);
+
+        // messages are received in order
+        let mut receiver = outbox.take_receiver().expect("Should have receiver");
+        assert!(outbox.take_receiver().is_none());
+        let message = receiver.try_recv().expect("Should receive");
+        assert_eq!(
+            message.decode_payload().expect("Should decode"),
+            b"result-0"
+        );
+        assert_eq!(outbox.len(), 1);
+    }
+
+    #[test]
+    fn test_outbox_degraded() {
+        let outbox = Outbox::new(2, Duration::from_secs(10));
+        assert!(!outbox.is_degraded());
+
+        outbox.record_latency(Duration::from_secs(11));
+        assert!(outbox.is_degraded());
+
+        // recovers with the next fast publish
+        outbox.record_latency(Duration::from_secs(1));
+        assert!(!outbox.is_degraded());
+    }
+}
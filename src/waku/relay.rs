@@ -1,8 +1,15 @@
-use crate::{errors::NodeResult, utils::http::BaseClient};
+use crate::{
+    errors::NodeResult,
+    utils::{http::BaseClient, units::env_size},
+};
+use std::time::{Duration, Instant};
 use urlencoding;
 
 use super::message::WakuMessage;
 
+/// Default maximum message size of nwaku, 150 KiB.
+pub const DEFAULT_DKN_WAKU_MAX_MESSAGE_SIZE: usize = 150 * 1024;
+
 /// Client for [11/WAKU2-RELAY](https://github.com/vacp2p/rfc-index/blob/main/waku/standards/core/11/relay.md) operations.
 ///
 /// The relay client is used to send and receive messages to Waku network. It works as follows:
@@ -10,25 +17,79 @@ use super::message::WakuMessage;
 /// 1. A node subscribes to a content topic
 /// 2. Nodes that are subscribed to the same content topic can send and receive messages via the network.
 /// 3. On termination, the node unsubscribes from the content topic.
+///
+/// Messages larger than `DKN_WAKU_MAX_MESSAGE_SIZE`, e.g. `150KiB`, are rejected before sending, as the relay would drop them.
 #[derive(Debug, Clone)]
 pub struct RelayClient {
     base: BaseClient,
+    max_message_size: usize,
 }
 
 impl RelayClient {
     pub fn new(base: BaseClient) -> Self {
-        RelayClient { base }
+        let max_message_size = env_size("DKN_WAKU_MAX_MESSAGE_SIZE")
+            .unwrap_or_else(|e| panic!("Invalid configuration: {}", e))
+            .unwrap_or(DEFAULT_DKN_WAKU_MAX_MESSAGE_SIZE);
+
+        RelayClient {
+            base,
+            max_message_size,
+        }
     }
 
-    /// Send a message.
+    /// Returns the URL of the Waku node behind this relay.
+    pub fn get_base_url(&self) -> String {
+        self.base.get_base_url()
+    }
+
+    /// Health-checks the Waku node behind this relay, and returns the round-trip time of the check.
+    ///
+    /// The check fails if the relay does not respond within the given timeout.
+    pub async fn probe(&self, timeout: Duration) -> NodeResult<Duration> {
+        let start = Instant::now();
+        let msg = tokio::time::timeout(timeout, async {
+            let res = self.base.get("health", None).await?;
+            NodeResult::Ok(res.text().await?)
+        })
+        .await??;
+        if msg != "Node is healthy" {
+            return Err(msg.into());
+        }
+
+        Ok(start.elapsed())
+    }
+
+    /// Send a message, if its encoded size is within the limits of the relay.
     pub async fn send_message(&self, message: WakuMessage) -> NodeResult<()> {
         log::info!("Sending: {}", message);
-        let message = serde_json::json!(message);
+        let message = serde_json::to_value(&message)?;
+
+        let size = message.to_string().len();
+        if size > self.max_message_size {
+            return Err(format!(
+                "Message of {} bytes exceeds the relay limit of {} bytes",
+                size, self.max_message_size
+            )
+            .into());
+        }
+        log::debug!("Sending message of {} bytes", size);
+
         self.base.post("relay/v1/auto/messages", message).await?;
 
         Ok(())
     }
 
+    /// Send a message on a content topic, where the topic is subscribed, the message is sent, and
+    /// the topic is unsubscribed right afterwards.
+    pub async fn send_message_once(&self, message: WakuMessage) -> NodeResult<()> {
+        let content_topic = message.content_topic.clone();
+        self.subscribe(&content_topic).await?;
+        self.send_message(message).await?;
+        self.unsubscribe(&content_topic).await?;
+
+        Ok(())
+    }
+
     /// Get messages with a given content topic.
     ///
     /// The content topic must have been subscribed to before.
@@ -75,3 +136,22 @@ impl RelayClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_message_size_limit() {
+        let mut relay = RelayClient::new(BaseClient::new("http://127.0.0.1:1".to_string()));
+        relay.max_message_size = 100;
+
+        // fails before reaching the relay
+        let message = WakuMessage::new(vec![0u8; 100], "test-topic");
+        let err = relay
+            .send_message(message)
+            .await
+            .expect_err("Should exceed limit");
+        assert!(err.to_string().contains("exceeds the relay limit"));
+    }
+}
@@ -0,0 +1,104 @@
+//! Serde support for nanosecond timestamps as `u128`, which interoperate with nwaku and JS clients.
+//!
+//! Waku defines timestamps as 64-bit signed integers in nanoseconds, and JS clients can only represent such
+//! values exactly as strings or bigints. Timestamps are therefore serialized as `i64` numbers with an overflow
+//! check, and deserialized from either numbers or strings.
+//!
+//! Use with `#[serde(with = "crate::waku::timestamp")]`.
+
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+/// Converts a timestamp to `i64`, returns `None` if it overflows.
+#[inline]
+pub fn to_i64(timestamp: u128) -> Option<i64> {
+    i64::try_from(timestamp).ok()
+}
+
+/// Converts an `i64` timestamp, returns `None` if it is negative.
+#[inline]
+pub fn from_i64(timestamp: i64) -> Option<u128> {
+    u128::try_from(timestamp).ok()
+}
+
+pub fn serialize<S: Serializer>(timestamp: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    let timestamp = to_i64(*timestamp)
+        .ok_or_else(|| ser::Error::custom(format!("timestamp {} overflows i64", timestamp)))?;
+    serializer.serialize_i64(timestamp)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Number(i64),
+        String(String),
+    }
+
+    let timestamp = match Timestamp::deserialize(deserializer)? {
+        Timestamp::Number(timestamp) => timestamp,
+        Timestamp::String(timestamp) => timestamp
+            .trim()
+            .parse::<i64>()
+            .map_err(|e| de::Error::custom(format!("invalid timestamp {}: {}", timestamp, e)))?,
+    };
+
+    from_i64(timestamp)
+        .ok_or_else(|| de::Error::custom(format!("timestamp {} is negative", timestamp)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TestStruct {
+        #[serde(with = "super")]
+        timestamp: u128,
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(to_i64(1714129073557846272), Some(1714129073557846272));
+        assert_eq!(to_i64(i64::MAX as u128 + 1), None);
+        assert_eq!(from_i64(1714129073557846272), Some(1714129073557846272));
+        assert_eq!(from_i64(-1), None);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let expected = TestStruct {
+            timestamp: 1714129073557846272,
+        };
+
+        let number: TestStruct = serde_json::from_str(r#"{"timestamp":1714129073557846272}"#)
+            .expect("Should parse number");
+        assert_eq!(number, expected);
+
+        let string: TestStruct = serde_json::from_str(r#"{"timestamp":"1714129073557846272"}"#)
+            .expect("Should parse string");
+        assert_eq!(string, expected);
+
+        assert!(serde_json::from_str::<TestStruct>(r#"{"timestamp":-1}"#).is_err());
+        assert!(serde_json::from_str::<TestStruct>(r#"{"timestamp":"soon"}"#).is_err());
+        assert!(
+            serde_json::from_str::<TestStruct>(r#"{"timestamp":"9223372036854775808"}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let value = TestStruct {
+            timestamp: 1714129073557846272,
+        };
+        assert_eq!(
+            serde_json::to_string(&value).expect("Should serialize"),
+            r#"{"timestamp":1714129073557846272}"#
+        );
+
+        let overflow = TestStruct {
+            timestamp: u128::MAX,
+        };
+        assert!(serde_json::to_string(&overflow).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{node::DriaComputeNode, utils::capabilities::CapabilitiesExporter};
+
+/// # Capabilities Worker
+///
+/// Exports the signed capabilities of the node at regular intervals, regardless of heartbeats, so that the
+/// busy state is kept up to date as well. Does nothing unless `DKN_CAPABILITIES_FILE` or `DKN_CAPABILITIES_URL`
+/// is given, see [`CapabilitiesExporter`].
+pub fn capabilities_worker(
+    node: Arc<DriaComputeNode>,
+    sleep_amount: Duration,
+) -> tokio::task::JoinHandle<()> {
+    let exporter = CapabilitiesExporter::from_env();
+
+    tokio::spawn(async move {
+        let exporter = match exporter {
+            Some(exporter) => exporter,
+            None => return,
+        };
+
+        loop {
+            tokio::select! {
+                _ = node.cancellation.cancelled() => break,
+                _ = tokio::time::sleep(sleep_amount) => {
+                    if let Err(e) = exporter.export(&node.capabilities()).await {
+                        log::error!("Error exporting capabilities: {}", e);
+                    }
+                }
+            }
+        }
+    })
+}
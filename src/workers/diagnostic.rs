@@ -1,32 +1,130 @@
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::node::DriaComputeNode;
+use crate::{
+    node::DriaComputeNode,
+    utils::{
+        alert::{alert_after_from_env, AlertCondition, Alerter},
+        doh::DohResolver,
+    },
+};
 
 /// # Diagnostic Worker
 ///
 /// This is a simple worker that keeps track of the node information, and prints it at regular intervals.
 /// In particular, it will print the number of peers.
+///
+/// When multiple relays are configured, it also re-evaluates which relay is used for publishing.
+/// When a DNS-over-HTTPS resolver is configured, its counters are printed as well.
+/// When archiving with a retention window, expired archive files are purged.
+///
+/// The state of the outbox is printed as well, i.e. whether publishing is degraded, and how many results are waiting or
+/// were dropped.
+///
+/// It also raises alerts when the relay is unreachable, there are no peers, messages keep failing signature
+/// verification, or publishing is degraded for longer than `DKN_ALERT_AFTER`, see [`Alerter`].
 pub fn diagnostic_worker(
     node: Arc<DriaComputeNode>,
     sleep_amount: Duration,
 ) -> tokio::task::JoinHandle<()> {
+    let alerter = Alerter::from_env();
+    let alert_after = alert_after_from_env();
+    let mut relay_disconnected = AlertCondition::new("relay_disconnected", alert_after);
+    let mut no_peers = AlertCondition::new("no_peers", alert_after);
+    let mut signature_failures = AlertCondition::new("signature_failures", alert_after);
+    let mut publish_degraded = AlertCondition::new("publish_degraded", alert_after);
+
     tokio::spawn(async move {
+        let mut last_signature_failures = 0;
         loop {
             tokio::select! {
                 _ = node.cancellation.cancelled() => break,
                 _ = tokio::time::sleep(sleep_amount) => {
+                    node.waku.select_relay().await;
+
+                    if let Some(resolver) = DohResolver::shared() {
+                        let stats = resolver.stats();
+                        log::info!(
+                            "DoH {}: {} resolved, {} fallbacks, {} failures",
+                            resolver.url(),
+                            stats.resolved.load(Ordering::Relaxed),
+                            stats.fallbacks.load(Ordering::Relaxed),
+                            stats.failures.load(Ordering::Relaxed)
+                        );
+                    }
+
+                    if let Some(archive) = &node.archive {
+                        let archive = archive.clone();
+                        match tokio::task::spawn_blocking(move || archive.purge()).await {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(e)) => log::error!("Error purging archive: {}", e),
+                            Err(e) => log::error!("Error purging archive: {}", e),
+                        }
+                    }
+
+                    #[cfg(feature = "crypto_timing")]
+                    for (op, histogram) in crate::utils::timing::snapshot() {
+                        log::info!(
+                            "Crypto {}: {} ops, mean {:?}, p99 < {:?}, max {:?}",
+                            op,
+                            histogram.count,
+                            histogram.mean(),
+                            histogram.quantile(0.99),
+                            histogram.max
+                        );
+                    }
 
-                    match node.waku.peers().await {
+                    let peers = node.waku.peers().await;
+                    match &peers {
                         Ok(peers) => {
                             log::info!("Active number of peers: {}", peers.len());
                         },
                         Err(e) => {
                             log::error!("Error getting peers: {}", e);
-                            continue;
                         }
                     };
 
+                    let oversized = node.oversized_tasks.load(Ordering::Relaxed);
+                    let unverified = node.unverified_oversized.load(Ordering::Relaxed);
+                    if oversized > 0 || unverified > 0 {
+                        log::info!(
+                            "Messages over size limits: {} tasks deprioritized, {} unverified messages dropped",
+                            oversized,
+                            unverified
+                        );
+                    }
+
+                    let degraded = node.outbox.is_degraded();
+                    let (queued, dropped) = (node.outbox.len(), node.outbox.dropped());
+                    if degraded || queued > 0 || dropped > 0 {
+                        log::info!(
+                            "Outbox: {}, {} results waiting, {} dropped",
+                            if degraded { "degraded" } else { "healthy" },
+                            queued,
+                            dropped
+                        );
+                    }
+
+                    let failures = node.signature_failures.load(Ordering::Relaxed);
+                    let now = Instant::now();
+                    let conditions = [
+                        (&mut relay_disconnected, peers.is_err()),
+                        (&mut no_peers, peers.as_ref().is_ok_and(|peers| peers.is_empty())),
+                        (&mut signature_failures, failures > last_signature_failures),
+                        (&mut publish_degraded, degraded),
+                    ];
+                    last_signature_failures = failures;
+
+                    for (condition, holds) in conditions {
+                        if condition.update(holds, now) {
+                            let message = format!("{} for {:?}", condition.name, condition.duration(now));
+                            log::error!("Alert: {}", message);
+                            if let Some(alerter) = &alerter {
+                                alerter.alert(&hex::encode(node.address()), condition.name, &message).await;
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::{node::DriaComputeNode, utils::crypto::sha256hash, waku::message::WakuMessage};
+use crate::{node::DriaComputeNode, utils::crypto::sha256hash};
 
 use serde::{Deserialize, Serialize};
 
@@ -46,6 +46,12 @@ pub fn heartbeat_worker(
                         continue;
                     }
 
+                    // otherwise the node would be assigned tasks whose results never reach their requesters
+                    if node.dry_run.is_some() {
+                        log::info!("Dry-run is enabled, skipping heartbeat.");
+                        continue;
+                    }
+
                     // we only care about the latest heartbeat
                     if let Some(message) = messages.last() {
                         log::info!("Received: {}", message);
@@ -55,7 +61,7 @@ pub fn heartbeat_worker(
                             Ok(body) => {
                                 let uuid = body.uuid;
                                 let signature = node.sign_bytes(&sha256hash(uuid.as_bytes()));
-                                WakuMessage::new(signature, &uuid)
+                                node.create_message(signature, &uuid)
                             }
                             Err(e) => {
                                 log::error!("Error parsing payload: {}", e);
@@ -104,7 +110,8 @@ mod tests {
             content_topic: "/dria/0/heartbeat/proto".to_string(), 
             version: 0,
             timestamp: 1714129073557846272,
-            ephemeral: true
+            ephemeral: true,
+            meta: None,
         };
 
         assert!(message.is_signed(&pk).expect("Should check signature"));
@@ -1,5 +1,7 @@
+pub mod capabilities;
 pub mod diagnostic;
 pub mod heartbeat;
+pub mod publish;
 
 #[cfg(feature = "synthesis")]
 pub mod synthesis;
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::node::DriaComputeNode;
+
+/// # Publish Worker
+///
+/// Publishes the results in the outbox of the node one by one, so that task handlers do not wait on the relay,
+/// see [`DriaComputeNode::send_result`]. On termination, the results that are still in the outbox are published
+/// before returning.
+pub fn publish_worker(node: Arc<DriaComputeNode>) -> tokio::task::JoinHandle<()> {
+    let receiver = node.outbox.take_receiver();
+
+    tokio::spawn(async move {
+        let mut receiver = match receiver {
+            Some(receiver) => receiver,
+            None => {
+                log::error!("Outbox is already being published, exiting publish worker.");
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = node.cancellation.cancelled() => break,
+                message = receiver.recv() => match message {
+                    Some(message) => {
+                        if let Err(e) = node.publish_result(message).await {
+                            log::error!("Error publishing result: {}", e);
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        // publish what is left, as these results are already computed
+        receiver.close();
+        while let Ok(message) = receiver.try_recv() {
+            if let Err(e) = node.publish_result(message).await {
+                log::error!("Error publishing result: {}", e);
+            }
+        }
+    })
+}
@@ -1,11 +1,11 @@
+use serde_json::json;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::{
-    compute::{payload::TaskRequestPayload, search_python::SearchPythonClient},
+    compute::{limits::TaskSize, payload::TaskRequestPayload, search_python::SearchPythonClient},
     node::DriaComputeNode,
-    utils::get_current_time_nanos,
-    waku::message::WakuMessage,
+    utils::archive::ArchiveKind,
 };
 
 /// # Search Payload
@@ -31,6 +31,7 @@ pub fn search_worker(
                 }
                 _ = tokio::time::sleep(sleep_amount) => {
                     let mut tasks = Vec::new();
+                    let mut oversized_tasks = Vec::new();
                     if let Ok(messages) = node.process_topic(topic, true).await {
                         if messages.is_empty() {
                             continue;
@@ -38,10 +39,13 @@ pub fn search_worker(
                         log::info!("Received {} search-python tasks.", messages.len());
 
                         for message in messages {
+                            // tasks over the hard limit are already refused by `process_topic`
+                            let task_size = node.task_limits.check(message.payload_size());
+
                             match message.parse_payload::<SearchPayload>(true) {
                                 Ok(task) => {
                                     // check deadline
-                                    if get_current_time_nanos() >= task.deadline {
+                                    if task.is_expired(node.clock.as_ref()) {
                                         log::debug!("{}", format!("Skipping {} due to deadline.", task.task_id));
                                         continue;
                                     }
@@ -60,7 +64,18 @@ pub fn search_worker(
                                         }
                                     }
 
-                                    tasks.push(task);
+                                    // check region constraints
+                                    if !task.allows_region(node.config.DKN_REGION.as_deref()) {
+                                        log::info!("Skipping {} due to region constraints.", task.task_id);
+                                        continue;
+                                    }
+
+                                    node.archive(ArchiveKind::Task, &task);
+                                    if task_size == TaskSize::Oversized {
+                                        oversized_tasks.push(task);
+                                    } else {
+                                        tasks.push(task);
+                                    }
                                 },
                                 Err(e) => {
                                     log::error!("Error parsing payload: {}", e);
@@ -69,10 +84,16 @@ pub fn search_worker(
                             }
                         }
                     }
+                    // oversized tasks are processed last
+                    tasks.append(&mut oversized_tasks);
+
                     // Set node to busy
                     node.set_busy(true);
+                    node.set_current_tasks(tasks.iter().map(|task| task.task_id.clone()).collect());
 
                     for task in tasks {
+                        node.metrics.task_started(topic);
+
                         // parse public key
                         let task_public_key = match hex::decode(&task.public_key) {
                             Ok(public_key) => public_key,
@@ -82,16 +103,25 @@ pub fn search_worker(
                             }
                         };
 
+                        let started = Instant::now();
                         let search_result = match search_client.search(task.input).await {
-                            Ok(search_result) => search_result,
+                            Ok(search_result) => {
+                                node.metrics.provider_latency("search_python", started.elapsed());
+                                search_result
+                            },
                             Err(e) => {
                                 log::error!("Error searching: {}", e);
                                 continue;
                             }
                         };
 
+                        node.archive(ArchiveKind::Result, &json!({
+                            "taskId": task.task_id,
+                            "result": search_result,
+                        }));
+
                         // create h||s||e payload
-                        let payload = match node.create_payload(search_result, &task_public_key) {
+                        let mut payload = match node.create_payload(search_result, &task_public_key) {
                             Ok(payload) => payload,
                             Err(e) => {
                                 log::error!("Error creating payload: {}", e);
@@ -99,6 +129,8 @@ pub fn search_worker(
                             }
                         };
 
+                        node.acknowledge_region(&mut payload, &task.task_id, &task.regions);
+
                         // stringify payload
                         let payload_str = match payload.to_string() {
                             Ok(payload_str) => payload_str,
@@ -109,16 +141,18 @@ pub fn search_worker(
                         };
 
                         // send result to Waku network
-                        let message = WakuMessage::new(payload_str, &task.task_id);
-                        if let Err(e) = node.send_message_once(message)
+                        let message = node.create_message(payload_str, &task.task_id.to_string());
+                        if let Err(e) = node.send_result(message)
                             .await {
                                 log::error!("Error sending message: {}", e);
                                 continue;
                             }
+                        node.metrics.task_completed(topic);
                     }
 
                     // Set node to not busy
                     node.set_busy(false);
+                    node.set_current_tasks(Vec::new());
                 }
             }
         }
@@ -1,11 +1,13 @@
+use serde_json::json;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::{
-    compute::{ollama::OllamaClient, payload::TaskRequestPayload},
+    compute::{
+        limits::TaskSize, ollama::OllamaClient, payload::TaskRequestPayload, shadow::Shadow,
+    },
     node::DriaComputeNode,
-    utils::get_current_time_nanos,
-    waku::message::WakuMessage,
+    utils::archive::ArchiveKind,
 };
 
 /// # Synthesis Payload
@@ -20,11 +22,17 @@ pub fn synthesis_worker(
     sleep_amount: Duration,
 ) -> tokio::task::JoinHandle<()> {
     let ollama = OllamaClient::new(None, None, None);
+    let shadow = Shadow::from_env();
 
     tokio::spawn(async move {
         if let Err(e) = ollama.setup(node.cancellation.clone()).await {
             log::error!("Could not setup Ollama: {}", e);
         }
+        if let Some(shadow) = &shadow {
+            if let Err(e) = shadow.ollama.setup(node.cancellation.clone()).await {
+                log::error!("Could not setup shadow Ollama: {}", e);
+            }
+        }
 
         node.subscribe_topic(topic).await;
 
@@ -38,6 +46,7 @@ pub fn synthesis_worker(
                 }
                 _ = tokio::time::sleep(sleep_amount) => {
                     let mut tasks = Vec::new();
+                    let mut oversized_tasks = Vec::new();
                     if let Ok(messages) = node.process_topic(topic, true).await {
                         if messages.is_empty() {
                             continue;
@@ -45,10 +54,13 @@ pub fn synthesis_worker(
                         log::info!("Received {} synthesis tasks.", messages.len());
 
                         for message in messages {
+                            // tasks over the hard limit are already refused by `process_topic`
+                            let task_size = node.task_limits.check(message.payload_size());
+
                             match message.parse_payload::<SynthesisPayload>(true) {
                                 Ok(task) => {
                                     // check deadline
-                                    if get_current_time_nanos() >= task.deadline {
+                                    if task.is_expired(node.clock.as_ref()) {
                                         log::debug!("{}", format!("Skipping {} due to deadline.", task.task_id));
                                         continue;
                                     }
@@ -67,7 +79,18 @@ pub fn synthesis_worker(
                                         }
                                     }
 
-                                    tasks.push(task);
+                                    // check region constraints
+                                    if !task.allows_region(node.config.DKN_REGION.as_deref()) {
+                                        log::info!("Skipping {} due to region constraints.", task.task_id);
+                                        continue;
+                                    }
+
+                                    node.archive(ArchiveKind::Task, &task);
+                                    if task_size == TaskSize::Oversized {
+                                        oversized_tasks.push(task);
+                                    } else {
+                                        tasks.push(task);
+                                    }
                                 },
                                 Err(e) => {
                                     log::error!("Error parsing payload: {}", e);
@@ -76,10 +99,16 @@ pub fn synthesis_worker(
                             }
                         }
                     }
+                    // oversized tasks are processed last
+                    tasks.append(&mut oversized_tasks);
+
                     // Set node to busy
                     node.set_busy(true);
+                    node.set_current_tasks(tasks.iter().map(|task| task.task_id.clone()).collect());
 
                     for task in tasks {
+                        node.metrics.task_started(topic);
+
                         // parse public key
                         let task_public_key = match hex::decode(&task.public_key) {
                             Ok(public_key) => public_key,
@@ -89,17 +118,32 @@ pub fn synthesis_worker(
                             }
                         };
 
+                        // keep the prompt for the shadow, if this task is sampled
+                        let shadow_input = shadow
+                            .as_ref()
+                            .filter(|shadow| shadow.is_sampled(&task.task_id))
+                            .map(|_| task.input.clone());
+
                         // get prompt result from Ollama
+                        let started = Instant::now();
                         let llm_result = match ollama.generate(task.input).await {
-                            Ok(result) => result,
+                            Ok(result) => {
+                                node.metrics.provider_latency("ollama", started.elapsed());
+                                result
+                            },
                             Err(e) => {
                                 log::error!("Error generating prompt result: {}", e);
                                 continue;
                             }
                         };
 
+                        node.archive(ArchiveKind::Result, &json!({
+                            "taskId": task.task_id,
+                            "result": llm_result.response,
+                        }));
+
                         // create h||s||e payload
-                        let payload = match node.create_payload(llm_result.response, &task_public_key) {
+                        let mut payload = match node.create_payload(&llm_result.response, &task_public_key) {
                             Ok(payload) => payload,
                             Err(e) => {
                                 log::error!("Error creating payload: {}", e);
@@ -107,6 +151,8 @@ pub fn synthesis_worker(
                             }
                         };
 
+                        node.acknowledge_region(&mut payload, &task.task_id, &task.regions);
+
                         // stringify payload
                         let payload_str = match payload.to_string() {
                             Ok(payload_str) => payload_str,
@@ -117,16 +163,23 @@ pub fn synthesis_worker(
                         };
 
                         // send result to Waku network
-                        let message = WakuMessage::new(payload_str, &task.task_id);
-                        if let Err(e) = node.send_message_once(message)
+                        let message = node.create_message(payload_str, &task.task_id.to_string());
+                        if let Err(e) = node.send_result(message)
                             .await {
                                 log::error!("Error sending message: {}", e);
                                 continue;
                             }
+                        node.metrics.task_completed(topic);
+
+                        // run the shadow in the background after publishing, its result is only compared locally
+                        if let (Some(shadow), Some(input)) = (&shadow, shadow_input) {
+                            shadow.spawn(task.task_id, input, llm_result.response);
+                        }
                     }
 
                     // Set node to not busy
                     node.set_busy(false);
+                    node.set_current_tasks(Vec::new());
                 }
             }
         }